@@ -0,0 +1,207 @@
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+/// 列表与字典的最大嵌套层数，正常的种子文件远不到这个深度，避免恶意数据耗尽栈空间
+const MAX_DEPTH: usize = 64;
+
+/// bencode 值，字典按键的字节序排列，与规范编码的顺序一致
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Value>),
+    Dict(BTreeMap<Vec<u8>, Value>),
+}
+
+impl Value {
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Dict(dict) => dict.get(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut Value> {
+        match self {
+            Value::Dict(dict) => dict.get_mut(key.as_bytes()),
+            _ => None,
+        }
+    }
+
+    /// 设置字典中的值，不是字典时不做任何事
+    pub fn insert(&mut self, key: &str, value: Value) {
+        if let Value::Dict(dict) = self {
+            dict.insert(key.as_bytes().to_vec(), value);
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::Bytes(bytes) => std::str::from_utf8(bytes).ok(),
+            _ => None,
+        }
+    }
+
+    /// 非负整数，如文件长度
+    pub fn as_int(&self) -> Option<u64> {
+        match self {
+            Value::Int(i) => u64::try_from(*i).ok(),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&[Value]> {
+        match self {
+            Value::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    pub fn string(s: &str) -> Value {
+        Value::Bytes(s.as_bytes().to_vec())
+    }
+
+    /// 编码为规范的 bencode
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![];
+        self.encode_into(&mut out);
+        out
+    }
+
+    fn encode_into(&self, out: &mut Vec<u8>) {
+        match self {
+            Value::Int(i) => out.extend_from_slice(format!("i{}e", i).as_bytes()),
+            Value::Bytes(bytes) => {
+                out.extend_from_slice(format!("{}:", bytes.len()).as_bytes());
+                out.extend_from_slice(bytes);
+            }
+            Value::List(list) => {
+                out.push(b'l');
+                list.iter().for_each(|v| v.encode_into(out));
+                out.push(b'e');
+            }
+            Value::Dict(dict) => {
+                out.push(b'd');
+                for (key, value) in dict {
+                    Value::Bytes(key.clone()).encode_into(out);
+                    value.encode_into(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+}
+
+/// 解码一个完整的 bencode 值，同时返回顶层字典中 `key` 对应的值在原始数据中的范围，
+/// 用于按原始字节计算 infohash，不受重新编码时键顺序的影响
+pub fn decode_with_span(data: &[u8], key: &str) -> Result<(Value, Option<Range<usize>>), String> {
+    let mut parser = Parser { data, pos: 0, depth: 0, key: key.as_bytes(), span: None };
+    let value = parser.value()?;
+    if parser.pos != data.len() {
+        return Err(format!("Trailing data at byte {}", parser.pos));
+    }
+    Ok((value, parser.span))
+}
+
+struct Parser<'a> {
+    data: &'a [u8],
+    pos: usize,
+    depth: usize,
+    key: &'a [u8],
+    span: Option<Range<usize>>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Result<u8, String> {
+        self.data.get(self.pos).copied().ok_or_else(|| "Unexpected end of data".to_string())
+    }
+
+    /// 读取到 `end` 为止的十进制整数，并跳过 `end`
+    fn number(&mut self, end: u8) -> Result<i64, String> {
+        let start = self.pos;
+        let len = self.data[start..].iter().position(|&b| b == end).ok_or("Unterminated integer")?;
+        self.pos += len + 1;
+        std::str::from_utf8(&self.data[start..start + len])
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| format!("Invalid integer at byte {}", start))
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>, String> {
+        let start = self.pos;
+        let len = usize::try_from(self.number(b':')?).map_err(|_| format!("Invalid string length at byte {}", start))?;
+        let bytes = self.data.get(self.pos..self.pos + len).ok_or("String exceeds the end of data")?;
+        self.pos += len;
+        Ok(bytes.to_vec())
+    }
+
+    /// 进入一层列表或字典
+    fn enter(&mut self) -> Result<(), String> {
+        self.pos += 1;
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(format!("Nesting deeper than {} levels at byte {}", MAX_DEPTH, self.pos - 1));
+        }
+        Ok(())
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        match self.peek()? {
+            b'i' => {
+                self.pos += 1;
+                Ok(Value::Int(self.number(b'e')?))
+            }
+            b'0'..=b'9' => Ok(Value::Bytes(self.bytes()?)),
+            b'l' => {
+                self.enter()?;
+                let mut list = vec![];
+                while self.peek()? != b'e' {
+                    list.push(self.value()?);
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(Value::List(list))
+            }
+            b'd' => {
+                self.enter()?;
+                let mut dict = BTreeMap::new();
+                while self.peek()? != b'e' {
+                    let key = self.bytes()?;
+                    let start = self.pos;
+                    let value = self.value()?;
+                    if self.depth == 1 && key == self.key {
+                        self.span = Some(start..self.pos);
+                    }
+                    dict.insert(key, value);
+                }
+                self.pos += 1;
+                self.depth -= 1;
+                Ok(Value::Dict(dict))
+            }
+            other => Err(format!("Unexpected byte {:?} at {}", other as char, self.pos)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_19_48_27() {
+        let data = b"d8:announce3:url4:infod6:lengthi12e4:name5:a.mkvee";
+        let (value, span) = decode_with_span(data, "info").unwrap();
+        let info = value.get("info").unwrap();
+        assert_eq!(info.get("name").and_then(Value::as_str), Some("a.mkv"));
+        assert_eq!(info.get("length"), Some(&Value::Int(12)));
+        assert_eq!(&data[span.unwrap()], info.encode().as_slice());
+        assert_eq!(value.encode(), data);
+
+        assert!(decode_with_span(b"d4:name", "info").is_err());
+        assert!(decode_with_span(b"i1ei2e", "info").is_err());
+        // 嵌套过深的数据返回错误而不是栈溢出
+        let nested = [vec![b'l'; MAX_DEPTH], vec![b'e'; MAX_DEPTH]].concat();
+        assert!(decode_with_span(&nested, "info").is_ok());
+        assert!(decode_with_span(&vec![b'l'; 200_000], "info").is_err());
+    }
+}
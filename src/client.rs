@@ -0,0 +1,245 @@
+use crate::deluge::Deluge;
+use crate::error::Error;
+use crate::local::LocalFs;
+use crate::plan::Rename;
+use crate::q_bit::QBittorrent;
+use crate::transmission::Transmission;
+use crate::{journal, log};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio::task::JoinSet;
+
+/// 未设置时同时进行的重命名请求数量
+const DEFAULT_MAX_CONCURRENCY: usize = 8;
+
+// 全局信号量限制同时进行的重命名请求数量，所有种子的种子名称、目录与文件重命名共用
+static RENAME_PERMITS: OnceLock<Semaphore> = OnceLock::new();
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TorrentInfo {
+    pub hash: String,
+    pub name: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct TorrentFile {
+    pub name: String,
+    pub index: u32,
+    /// 文件大小（字节），用于在同名的视频中找出正片
+    #[serde(default)]
+    pub size: u64,
+}
+
+/// 种子列表的筛选条件，未设置的条件不参与筛选
+#[derive(Debug, Default, Clone)]
+pub struct TorrentFilter {
+    pub hashes: Vec<String>,
+    pub category: Option<String>,
+    pub tag: Option<String>,
+    pub state: Option<String>,
+}
+
+/// 下载客户端需要提供的操作，种子内的路径均以 `/` 分隔且包含顶层目录
+pub trait TorrentClient {
+    async fn list_torrents(&self, filter: &TorrentFilter) -> Result<Vec<TorrentInfo>, Error>;
+
+    async fn get_files(&self, hash: &str) -> Result<Vec<TorrentFile>, Error>;
+
+    /// 是否支持单独修改种子的显示名称
+    fn supports_torrent_rename(&self) -> bool {
+        true
+    }
+
+    async fn rename_torrent(&self, hash: &str, name: &str) -> Result<(), Error>;
+
+    /// 重命名种子内的一个目录或文件，新旧路径只有最后一级不同
+    async fn rename_path(&self, hash: &str, rename: &Rename, is_dir: bool) -> Result<(), Error>;
+
+    /// 为种子添加标签（Transmission 中为 label），保留已有的标签；Deluge 每个种子只有一个 label，会被替换
+    async fn add_tags(&self, hash: &str, tags: &[String]) -> Result<(), Error>;
+
+    /// 一个种子的所有重命名都结束后调用，无论成功与否
+    async fn finish_renames(&self, _hash: &str) {}
+}
+
+/// 支持的下载客户端
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ClientKind {
+    #[default]
+    #[value(name = "qbittorrent")]
+    QBittorrent,
+    Transmission,
+    Deluge,
+}
+
+/// 通过 `--client` 选择的下载客户端，或 `fs` 子命令使用的本地目录
+pub enum Backend {
+    QBittorrent(QBittorrent),
+    Transmission(Transmission),
+    Deluge(Deluge),
+    Local(LocalFs),
+}
+
+impl Backend {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::QBittorrent(_) => "qBittorrent",
+            Backend::Transmission(_) => "Transmission",
+            Backend::Deluge(_) => "Deluge",
+            Backend::Local(_) => "the local filesystem",
+        }
+    }
+}
+
+impl TorrentClient for Backend {
+    async fn list_torrents(&self, filter: &TorrentFilter) -> Result<Vec<TorrentInfo>, Error> {
+        let torrents = match self {
+            Backend::QBittorrent(c) => c.list_torrents(filter).await?,
+            Backend::Transmission(c) => c.list_torrents(filter).await?,
+            Backend::Deluge(c) => c.list_torrents(filter).await?,
+            Backend::Local(c) => c.list_torrents(filter).await?,
+        };
+        log!("Fetched {} torrents", torrents.len());
+        Ok(torrents)
+    }
+
+    async fn get_files(&self, hash: &str) -> Result<Vec<TorrentFile>, Error> {
+        match self {
+            Backend::QBittorrent(c) => c.get_files(hash).await,
+            Backend::Transmission(c) => c.get_files(hash).await,
+            Backend::Deluge(c) => c.get_files(hash).await,
+            Backend::Local(c) => c.get_files(hash).await,
+        }
+    }
+
+    fn supports_torrent_rename(&self) -> bool {
+        match self {
+            Backend::QBittorrent(c) => c.supports_torrent_rename(),
+            Backend::Transmission(c) => c.supports_torrent_rename(),
+            Backend::Deluge(c) => c.supports_torrent_rename(),
+            Backend::Local(c) => c.supports_torrent_rename(),
+        }
+    }
+
+    async fn rename_torrent(&self, hash: &str, name: &str) -> Result<(), Error> {
+        match self {
+            Backend::QBittorrent(c) => c.rename_torrent(hash, name).await,
+            Backend::Transmission(c) => c.rename_torrent(hash, name).await,
+            Backend::Deluge(c) => c.rename_torrent(hash, name).await,
+            Backend::Local(c) => c.rename_torrent(hash, name).await,
+        }
+    }
+
+    async fn rename_path(&self, hash: &str, rename: &Rename, is_dir: bool) -> Result<(), Error> {
+        match self {
+            Backend::QBittorrent(c) => c.rename_path(hash, rename, is_dir).await,
+            Backend::Transmission(c) => c.rename_path(hash, rename, is_dir).await,
+            Backend::Deluge(c) => c.rename_path(hash, rename, is_dir).await,
+            Backend::Local(c) => c.rename_path(hash, rename, is_dir).await,
+        }
+    }
+
+    async fn add_tags(&self, hash: &str, tags: &[String]) -> Result<(), Error> {
+        match self {
+            Backend::QBittorrent(c) => c.add_tags(hash, tags).await,
+            Backend::Transmission(c) => c.add_tags(hash, tags).await,
+            Backend::Deluge(c) => c.add_tags(hash, tags).await,
+            Backend::Local(c) => c.add_tags(hash, tags).await,
+        }
+    }
+
+    async fn finish_renames(&self, hash: &str) {
+        match self {
+            Backend::QBittorrent(c) => c.finish_renames(hash).await,
+            Backend::Transmission(c) => c.finish_renames(hash).await,
+            Backend::Deluge(c) => c.finish_renames(hash).await,
+            Backend::Local(c) => c.finish_renames(hash).await,
+        }
+    }
+}
+
+// 设置同时进行的重命名请求数量
+pub fn set_max_concurrency(max: usize) {
+    RENAME_PERMITS.get_or_init(|| Semaphore::new(max));
+}
+
+/// 等待一个重命名请求的许可，许可在返回值被丢弃时归还
+async fn rename_permit() -> SemaphorePermit<'static> {
+    let permits = RENAME_PERMITS.get_or_init(|| Semaphore::new(DEFAULT_MAX_CONCURRENCY));
+    // 信号量从不关闭
+    permits.acquire().await.unwrap()
+}
+
+pub async fn rename_torrent(client: &Backend, torrent_hash: &str, rename: &Rename) -> Result<(), Error> {
+    let _permit = rename_permit().await;
+    client.rename_torrent(torrent_hash, &rename.new).await?;
+
+    log!("Successfully renamed torrent to: {}", rename.new);
+    journal::record(torrent_hash, rename);
+    Ok(())
+}
+
+/// 依次重命名目录，任一目录失败即停止，避免其下的文件按错误的路径改名；失败时同时返回已完成的目录数量
+pub async fn rename_folders(client: &Backend, torrent_hash: &str, renames: Vec<Rename>) -> Result<(), (usize, Error)> {
+    for (done, rename) in renames.into_iter().enumerate() {
+        let _permit = rename_permit().await;
+        client.rename_path(torrent_hash, &rename, true).await.map_err(|e| (done, e))?;
+
+        log!("Success: {} -> {}", rename.old, rename.new);
+        journal::record(torrent_hash, &rename);
+    }
+    Ok(())
+}
+
+pub async fn rename_files(client: &'static Backend, torrent_hash: &str, renames: Vec<Rename>) -> Vec<Error> {
+    let mut tasks = JoinSet::new();
+
+    // 并行处理每个文件重命名，同时发出的请求数量受 --max-concurrency 限制
+    for rename in renames {
+        let hash = torrent_hash.to_owned();
+        tasks.spawn(async move {
+            let _permit = rename_permit().await;
+            let result = client.rename_path(&hash, &rename, false).await;
+            // 返回处理结果与元数据
+            (rename, result)
+        });
+    }
+
+    // 统一处理所有任务结果，返回每个失败的错误
+    let mut errors = vec![];
+    while let Some(res) = tasks.join_next().await {
+        match res {
+            Ok((rename, Ok(_))) => {
+                log!("Success: {} -> {}", rename.old, rename.new);
+                journal::record(torrent_hash, &rename);
+            }
+            Ok((rename, Err(e))) => {
+                log!("Failed: {} -> {} | {}", rename.old, rename.new, e);
+                errors.push(e);
+            }
+            Err(e) => {
+                log!("Task execution failed: {}",e);
+                errors.push(Error::Network(format!("Task execution failed: {}", e)));
+            }
+        }
+    }
+    errors
+}
+
+/// 确认种子名称是否已经改好，用于重试前判断上一次请求是否已生效
+pub async fn torrent_renamed(client: &impl TorrentClient, torrent_hash: &str, name: &str) -> bool {
+    let filter = TorrentFilter { hashes: vec![torrent_hash.to_string()], ..Default::default() };
+    client.list_torrents(&filter).await.is_ok_and(|t| t.iter().any(|t| t.name == name))
+}
+
+/// 确认目录或文件是否已经改名：新路径已存在且旧路径不再存在，目录按其下的文件判断
+pub async fn path_renamed(client: &impl TorrentClient, torrent_hash: &str, rename: &Rename, is_dir: bool) -> bool {
+    let Ok(files) = client.get_files(torrent_hash).await else { return false };
+    let exists = |path: &str| match is_dir {
+        true => files.iter().any(|f| f.name.strip_prefix(path).is_some_and(|rest| rest.starts_with('/'))),
+        false => files.iter().any(|f| f.name == path),
+    };
+    exists(&rename.new) && !exists(&rename.old)
+}
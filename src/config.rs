@@ -0,0 +1,209 @@
+use crate::error::Error;
+use crate::{secret, Args};
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command, CommandFactory, Parser};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+/// 配置文件，每个命名配置中的键就是命令行的长参数名（`-` 可写作 `_`）
+/// ```toml
+/// [profiles.home]
+/// webui_url = "http://localhost:8080"
+/// username = "admin"
+/// password = "adminadmin"
+/// rename_rules = ["/etc/torrent-tidy/rules.toml#anime"]
+/// retries = 5
+/// request_timeout = "10s"
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct ConfigFile {
+    #[serde(default)]
+    profiles: BTreeMap<String, toml::Table>,
+}
+
+/// 未指定 `--config` 时的配置文件位置：`$XDG_CONFIG_HOME/torrent-tidy/config.toml`，默认为 `~/.config/torrent-tidy/config.toml`
+fn default_path() -> Option<PathBuf> {
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").or_else(|| std::env::var_os("USERPROFILE")).map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_home.join("torrent-tidy").join("config.toml"))
+}
+
+/// 同一凭据的环境变量与几种参数，命令行或环境变量给出其中任一个时，配置中的整组都不再使用
+const CREDENTIALS: [(&str, [&str; 3]); 2] = [
+    (secret::USERNAME_ENV, ["username", "username-file", "username-stdin"]),
+    (secret::PASSWORD_ENV, ["password", "password-file", "password-stdin"]),
+];
+
+/// 解析命令行参数，指定了 `--profile` 时用配置文件中的值补充命令行中未给出的参数，命令行参数优先
+pub fn parse() -> Result<Args, Error> {
+    Ok(Args::parse_from(with_profile(std::env::args_os().collect(), &|name| std::env::var(name).ok())?))
+}
+
+/// 在命令行参数之后追加所选配置中的参数，配置选项写在子命令之前或之后都可以
+fn with_profile(mut argv: Vec<OsString>, env: &dyn Fn(&str) -> Option<String>) -> Result<Vec<OsString>, Error> {
+    // 先宽松地解析一遍，找出配置选项与命令行中已经给出的参数；缺少的必需参数可能由配置文件提供
+    let matches = Args::command().ignore_errors(true).get_matches_from(&argv);
+    let (command, sub_matches) = match matches.subcommand() {
+        Some((name, sub_matches)) => (Args::command().find_subcommand(name).cloned().unwrap(), sub_matches),
+        None => (Args::command(), &matches),
+    };
+    let global = |id: &str| sub_matches.get_one::<String>(id).or_else(|| matches.get_one::<String>(id)).cloned();
+
+    if let Some(name) = global("profile") {
+        let path = match global("config") {
+            Some(path) => PathBuf::from(path),
+            None => default_path().ok_or_else(|| Error::Config("Can not locate the config directory, use --config".to_string()))?,
+        };
+        let content = std::fs::read_to_string(&path)
+            .map_err(|e| Error::Config(format!("Failed to read config file {}: {}", path.display(), e)))?;
+        let mut config: ConfigFile = toml::from_str(&content)
+            .map_err(|e| Error::Config(format!("Invalid config file {}: {}", path.display(), e)))?;
+        let Some(profile) = config.profiles.remove(&name) else {
+            return Err(Error::Config(format!("No profile {:?} in config file {}", name, path.display())));
+        };
+        argv.extend(profile_args(&profile, &command, sub_matches, env)?);
+    }
+    Ok(argv)
+}
+
+/// 把配置中命令行未给出的参数转换为命令行参数，追加在已有参数之后；`env` 查询凭据的环境变量
+fn profile_args(profile: &toml::Table, command: &Command, matches: &ArgMatches, env: &dyn Fn(&str) -> Option<String>) -> Result<Vec<OsString>, Error> {
+    let mut args = vec![];
+    for (key, value) in profile {
+        let long = key.replace('_', "-");
+        // 其它子命令才有的参数在这里不适用，任何命令中都不存在的参数视为拼写错误
+        if !command.get_arguments().any(|a| a.get_long() == Some(&long)) {
+            if !is_known(&long) {
+                return Err(Error::Config(format!("Unknown option {:?} in profile", key)));
+            }
+            continue;
+        }
+        // 命令行与环境变量都优先于配置，凭据按整组判断，避免配置中的文件或标准输入盖过命令行给出的值
+        let family = CREDENTIALS.iter().find(|(_, family)| family.contains(&long.as_str()));
+        let given = |long: &str| {
+            command.get_arguments().find(|a| a.get_long() == Some(long)).is_some_and(|a| {
+                matches.value_source(a.get_id().as_str()) == Some(ValueSource::CommandLine)
+            })
+        };
+        if given(&long) || family.is_some_and(|(name, family)| env(name).is_some() || family.iter().any(|&long| given(long))) {
+            continue;
+        }
+        // 值与参数名写在同一项中，以 `-` 开头的值（如密码或规则）不会被当作另一个参数
+        let flag = format!("--{}", long);
+        let values = match value {
+            toml::Value::Array(values) => values.iter().collect(),
+            value => vec![value],
+        };
+        for value in values {
+            match value {
+                toml::Value::Boolean(true) => args.push(OsString::from(&flag)),
+                toml::Value::Boolean(false) => {}
+                toml::Value::String(s) => args.push(format!("{}={}", flag, s).into()),
+                toml::Value::Integer(i) => args.push(format!("{}={}", flag, i).into()),
+                other => return Err(Error::Config(format!("Unsupported value {} for {:?} in profile", other, key))),
+            }
+        }
+    }
+    Ok(args)
+}
+
+/// 参数是否存在于顶层命令或任一子命令中
+fn is_known(long: &str) -> bool {
+    let command = Args::command();
+    let has = |c: &Command| c.get_arguments().any(|a| a.get_long() == Some(long));
+    has(&command) || command.get_subcommands().any(has)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_23_05_12() {
+        let profile: toml::Table = toml::from_str(
+            r#"
+            webui_url = "http://localhost:8080"
+            password_file = "/etc/torrent-tidy/password"
+            username = "admin"
+            "#,
+        )
+        .unwrap();
+        let args = |argv: &[&str]| {
+            let matches = Args::command().ignore_errors(true).get_matches_from(argv);
+            profile_args(&profile, &Args::command(), &matches, &|_| None).unwrap()
+        };
+        // 命令行给出任一种密码来源时，配置中的 password_file 不再使用，也不会与 --password-stdin 冲突
+        for argv in [["torrent-tidy", "-p", "secret"], ["torrent-tidy", "--password-stdin", "--all"]] {
+            assert_eq!(args(&argv), ["--username=admin", "--webui-url=http://localhost:8080"]);
+        }
+        assert_eq!(args(&["torrent-tidy", "--username-file", "/tmp/user"]), ["--password-file=/etc/torrent-tidy/password", "--webui-url=http://localhost:8080"]);
+    }
+
+    #[test]
+    fn test_2026_10_18_23_11_40() {
+        let profile: toml::Table = toml::from_str(
+            r#"
+            webui_url = "http://localhost:8080"
+            username = "admin"
+            rename_rules = ["-=_", "/etc/torrent-tidy/rules.toml#anime"]
+            password = "-s3cret"
+            retries = 5
+            dry_run = true
+            vpn = false
+            interval = "10s"
+            "#,
+        )
+        .unwrap();
+        let args = |argv: &[&str]| {
+            let matches = Args::command().ignore_errors(true).get_matches_from(argv);
+            profile_args(&profile, &Args::command(), &matches, &|_| None).unwrap()
+        };
+        // 数组展开为多个参数，true 为开关，false 省略；interval 只属于 watch 子命令，在这里忽略
+        let all = [
+            "--dry-run",
+            "--password=-s3cret",
+            "--rename-rules=-=_",
+            "--rename-rules=/etc/torrent-tidy/rules.toml#anime",
+            "--retries=5",
+            "--username=admin",
+            "--webui-url=http://localhost:8080",
+        ];
+        assert_eq!(args(&["torrent-tidy"]), all);
+        // 以 - 开头的值仍是参数的值
+        let parsed = Args::try_parse_from(["torrent-tidy", "--all"].into_iter().map(OsString::from).chain(args(&["torrent-tidy"]))).unwrap();
+        assert_eq!(parsed.rules.rename_rules, ["-=_", "/etc/torrent-tidy/rules.toml#anime"]);
+        assert_eq!(parsed.webui.password.unwrap().into_inner(), "-s3cret");
+        // 命令行给出的参数优先
+        assert_eq!(args(&["torrent-tidy", "-w", "http://nas:8080", "-r", "c=d", "-p", "x"]), ["--dry-run", "--retries=5", "--username=admin"]);
+
+        // 环境变量同样优先
+        let matches = Args::command().ignore_errors(true).get_matches_from(["torrent-tidy"]);
+        let env = |name: &str| (name == secret::USERNAME_ENV).then(|| "root".to_string());
+        assert_eq!(profile_args(&profile, &Args::command(), &matches, &env).unwrap(), [&all[..5], &all[6..]].concat());
+
+        // 任何命令中都不存在的参数视为错误
+        let profile: toml::Table = toml::from_str(r#"webui_urll = "x""#).unwrap();
+        let matches = Args::command().ignore_errors(true).get_matches_from(["torrent-tidy"]);
+        assert!(matches!(profile_args(&profile, &Args::command(), &matches, &|_| None), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn test_2026_10_19_10_02_33() {
+        let path = std::env::temp_dir().join(format!("torrent-tidy-test-config-{}-{}", std::process::id(), "test_2026_10_19_10_02_33"));
+        std::fs::write(&path, "[profiles.home]\nwebui_url = \"http://localhost:8080\"\nrename_rules = [\"a=b\"]\ninterval = \"10s\"\n").unwrap();
+        // 配置选项写在子命令之前，配置中的参数补充给子命令
+        let argv = ["torrent-tidy", "--config", path.to_str().unwrap(), "--profile", "home", "watch"].map(OsString::from);
+        let argv = with_profile(argv.to_vec(), &|_| None);
+        std::fs::remove_file(&path).unwrap();
+        let Some(crate::Command::Watch { webui, interval, .. }) = Args::try_parse_from(argv.unwrap()).unwrap().command else {
+            panic!("expected the watch subcommand");
+        };
+        assert_eq!(webui.webui_url.as_deref(), Some("http://localhost:8080"));
+        assert_eq!(interval, std::time::Duration::from_secs(10));
+    }
+}
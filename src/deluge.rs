@@ -0,0 +1,285 @@
+use crate::client::{self, TorrentClient, TorrentFile, TorrentFilter, TorrentInfo};
+use crate::error::Error;
+use crate::plan::Rename;
+use crate::{log, retry};
+use reqwest::Client;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Deluge Web JSON-RPC 中未登录对应的错误码
+const NOT_AUTHENTICATED: i64 = 1;
+
+/// Deluge Web 界面的 JSON-RPC 客户端，会话保存在 `client` 的 Cookie 中
+/// https://deluge.readthedocs.io/en/latest/reference/webapi.html
+pub struct Deluge {
+    client: Client,
+    json_url: String,
+    password: Option<String>,
+    next_id: AtomicU64,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse {
+    #[serde(default)]
+    result: Value,
+    error: Option<RpcError>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcError {
+    message: String,
+    code: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcTorrent {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    state: String,
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    progress: f64,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcFiles {
+    #[serde(default)]
+    files: Vec<RpcFile>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcFile {
+    index: u32,
+    path: String,
+    #[serde(default)]
+    size: u64,
+}
+
+impl RpcTorrent {
+    /// 按 qBittorrent 的状态名称筛选，只支持 Deluge 中有对应状态的名称
+    fn matches_state(&self, state: &str) -> Result<bool, Error> {
+        Ok(match state {
+            "all" => true,
+            "downloading" => self.state == "Downloading",
+            "seeding" => self.state == "Seeding",
+            "completed" => self.progress >= 100.0,
+            "stopped" | "paused" => self.state == "Paused",
+            "checking" => self.state == "Checking",
+            "queued" => self.state == "Queued",
+            "errored" => self.state == "Error",
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "Deluge does not support the state filter {:?}, use one of all, downloading, seeding, completed, stopped, checking, queued, errored",
+                    state
+                )));
+            }
+        })
+    }
+}
+
+impl Deluge {
+    /// `url` 可以是 JSON-RPC 地址本身，也可以是 Web 界面的根地址
+    pub fn new(client: Client, url: String, password: Option<String>) -> Self {
+        let url = url.trim_end_matches('/');
+        let json_url = if url.ends_with("/json") { url.to_string() } else { format!("{}/json", url) };
+        Self { client, json_url, password, next_id: AtomicU64::new(1) }
+    }
+
+    /// 使用密码登录 Web 界面，并确保 Web 界面已连接到某个 Deluge 守护进程
+    pub async fn login(&self) -> Result<(), Error> {
+        match &self.password {
+            Some(password) => {
+                let ok: bool = self.send("auth.login", json!([password])).await?;
+                if !ok {
+                    return Err(Error::Auth("Invalid Deluge Web password".to_string()));
+                }
+                log!("Authentication successful");
+            }
+            None => log!("Skipping authentication as password was not provided."),
+        }
+
+        // 登录过程中不再因会话失效而重新登录
+        if !self.send::<bool>("web.connected", json!([])).await? {
+            // 每一项为 [id, 地址, 端口, 状态]
+            let hosts: Vec<(String, String, u16, String)> = self.send("web.get_hosts", json!([])).await?;
+            let Some((id, host, port, _)) = hosts.into_iter().next() else {
+                return Err(Error::NotFound("Deluge Web is not connected to any daemon and has no hosts configured".to_string()));
+            };
+            self.send::<Value>("web.connect", json!([id])).await?;
+            log!("Connected Deluge Web to daemon {}:{}", host, port);
+        }
+        Ok(())
+    }
+
+    async fn call<T: DeserializeOwned + Default>(&self, method: &str, params: Value) -> Result<T, Error> {
+        // 读取请求不会被当作已生效，因此总有结果
+        self.call_unless_applied(method, params, || async { false }).await.map(|v| v.unwrap())
+    }
+
+    /// 调用一次 RPC 方法，会话失效时不重新登录
+    async fn send<T: DeserializeOwned + Default>(&self, method: &str, params: Value) -> Result<T, Error> {
+        self.call_once(method, params, || async { false }).await.map(|v| v.unwrap())
+    }
+
+    /// 调用 RPC 方法，会话失效时重新登录并再发送一次
+    async fn call_unless_applied<T: DeserializeOwned + Default, F: Future<Output = bool>>(
+        &self,
+        method: &str,
+        params: Value,
+        applied: impl Fn() -> F,
+    ) -> Result<Option<T>, Error> {
+        match self.call_once(method, params.clone(), &applied).await {
+            Err(Error::Auth(_)) if self.password.is_some() => {
+                log!("Session expired, logging in again");
+                Box::pin(self.login()).await?;
+                self.call_once(method, params, &applied).await
+            }
+            result => result,
+        }
+    }
+
+    /// 调用一次 RPC 方法，重试前先用 `applied` 确认上一次请求是否已经生效，已生效时返回 `None`
+    async fn call_once<T: DeserializeOwned + Default, F: Future<Output = bool>>(
+        &self,
+        method: &str,
+        params: Value,
+        applied: impl Fn() -> F,
+    ) -> Result<Option<T>, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let request = self.client.post(&self.json_url).json(&json!({ "method": method, "params": params, "id": id }));
+        let Some(response) = retry::send_unless_applied(request, applied)
+            .await
+            .map_err(|e| Error::http(&format!("Deluge RPC {} failed", method), e))?
+        else {
+            return Ok(None);
+        };
+        let response: RpcResponse = response
+            .json()
+            .await
+            .map_err(|e| Error::http(&format!("Failed to parse Deluge RPC {} response", method), e))?;
+        if let Some(error) = response.error {
+            let message = format!("Deluge RPC {} failed: {}", method, error.message);
+            return Err(match error.code {
+                NOT_AUTHENTICATED => Error::Auth(message),
+                _ if error.message.contains("Unknown method") => Error::Unsupported(message),
+                _ => Error::Network(message),
+            });
+        }
+        // 无返回值的方法返回 null
+        let result = if response.result.is_null() { Ok(T::default()) } else { serde_json::from_value(response.result) };
+        result
+            .map(Some)
+            .map_err(|e| Error::Decode(format!("Failed to parse Deluge RPC {} result: {}", method, e)))
+    }
+
+    async fn get_files_with_index(&self, hash: &str) -> Result<Vec<RpcFile>, Error> {
+        let status: HashMap<String, Value> = self.call("web.get_torrent_status", json!([hash, ["files"]])).await?;
+        if status.is_empty() {
+            return Err(Error::NotFound(format!("No torrent found with hash: {}", hash)));
+        }
+        let files: RpcFiles = serde_json::from_value(json!(status))
+            .map_err(|e| Error::Decode(format!("Failed to parse Deluge torrent files: {}", e)))?;
+        Ok(files.files)
+    }
+}
+
+impl TorrentClient for Deluge {
+    async fn list_torrents(&self, filter: &TorrentFilter) -> Result<Vec<TorrentInfo>, Error> {
+        if filter.tag.is_some() {
+            return Err(Error::Unsupported("Deluge has no tags, use --category to select torrents by label".to_string()));
+        }
+        let mut filter_dict = json!({});
+        if !filter.hashes.is_empty() {
+            filter_dict["id"] = json!(filter.hashes);
+        }
+        let keys = ["name", "state", "label", "progress"];
+        let torrents: HashMap<String, RpcTorrent> = self.call("core.get_torrents_status", json!([filter_dict, keys])).await?;
+
+        let mut result = vec![];
+        for (hash, torrent) in torrents {
+            // Deluge 的 label 插件相当于 qBittorrent 的分类，label 均为小写
+            if let Some(category) = &filter.category && torrent.label != category.to_lowercase() {
+                continue;
+            }
+            if let Some(state) = &filter.state && !torrent.matches_state(state)? {
+                continue;
+            }
+            result.push(TorrentInfo { hash, name: torrent.name });
+        }
+        Ok(result)
+    }
+
+    async fn get_files(&self, hash: &str) -> Result<Vec<TorrentFile>, Error> {
+        let files = self.get_files_with_index(hash).await?;
+        Ok(files.into_iter().map(|f| TorrentFile { name: f.path, index: f.index, size: f.size }).collect())
+    }
+
+    /// Deluge 没有独立于文件的种子显示名称
+    fn supports_torrent_rename(&self) -> bool {
+        false
+    }
+
+    async fn rename_torrent(&self, _hash: &str, _name: &str) -> Result<(), Error> {
+        Err(Error::Unsupported("Deluge can not rename the display name of a torrent".to_string()))
+    }
+
+    async fn rename_path(&self, hash: &str, rename: &Rename, is_dir: bool) -> Result<(), Error> {
+        let applied = || client::path_renamed(self, hash, rename, is_dir);
+        if is_dir {
+            // 目录以 / 结尾，避免同前缀的其它目录被一起改名
+            let params = json!([hash, format!("{}/", rename.old), format!("{}/", rename.new)]);
+            self.call_unless_applied::<Value, _>("core.rename_folder", params, applied).await?;
+        } else {
+            // core.rename_files 按文件序号重命名，计划中已带有序号，只有撤销时才需要按路径查找
+            let index = match rename.index {
+                Some(index) => index,
+                None => {
+                    let files = self.get_files_with_index(hash).await?;
+                    let Some(file) = files.iter().find(|f| f.path == rename.old) else {
+                        return Err(Error::NotFound(format!("No file {} in torrent {}", rename.old, hash)));
+                    };
+                    file.index
+                }
+            };
+            let params = json!([hash, [[index, rename.new]]]);
+            self.call_unless_applied::<Value, _>("core.rename_files", params, applied).await?;
+        }
+        Ok(())
+    }
+
+    /// Deluge 的 label 插件每个种子只有一个 label，使用第一个标签，不存在时先创建
+    async fn add_tags(&self, hash: &str, tags: &[String]) -> Result<(), Error> {
+        let Some(tag) = tags.first() else { return Ok(()) };
+        let label = tag.to_lowercase();
+        let labels: Vec<String> = self.call("label.get_labels", json!([])).await?;
+        if !labels.contains(&label) {
+            self.call::<Value>("label.add", json!([label])).await?;
+        }
+        self.call::<Value>("label.set_torrent", json!([hash, label])).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_19_02_51() {
+        let json_url = |url: &str| Deluge::new(Client::new(), url.to_string(), None).json_url;
+        assert_eq!(json_url("http://localhost:8112/"), "http://localhost:8112/json");
+        assert_eq!(json_url("https://seedbox.example/deluge/json"), "https://seedbox.example/deluge/json");
+
+        let files: RpcFiles = serde_json::from_value(json!({
+            "files": [{"index": 1, "path": "Show/b.mkv", "size": 1, "offset": 0}, {"index": 0, "path": "Show/a.mkv"}]
+        }))
+        .unwrap();
+        assert_eq!(files.files[0].index, 1);
+        assert_eq!(files.files[1].path, "Show/a.mkv");
+    }
+}
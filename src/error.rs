@@ -0,0 +1,119 @@
+use reqwest::StatusCode;
+
+/// 访问 WebUI 或加载规则时的错误，按类型区分以便调用方决定重试、跳过还是终止
+#[derive(Debug)]
+pub enum Error {
+    /// 登录失败、会话失效或被拒绝访问（401/403）
+    Auth(String),
+    /// 找不到指定的种子或文件（404）
+    NotFound(String),
+    /// 重命名的目标已存在（409），或因冲突放弃了重命名
+    Conflict(String),
+    /// 连接失败、超时、其它非成功状态码，或客户端返回的其它错误
+    Network(String),
+    /// 响应内容无法解析
+    Decode(String),
+    /// 规则文件无法读取或规则无效
+    InvalidRules(String),
+    /// 所选的下载客户端不支持该操作
+    Unsupported(String),
+    /// 读写本地文件或目录失败
+    Io(String),
+    /// 配置文件无法读取、无效或找不到指定的配置，与命令行参数错误使用相同的退出码
+    Config(String),
+}
+
+impl Error {
+    /// 根据 reqwest 错误的状态码与类型归类，`context` 说明正在进行的操作
+    pub fn http(context: &str, e: reqwest::Error) -> Self {
+        let message = format!("{}: {}", context, e);
+        match e.status() {
+            Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => Error::Auth(message),
+            Some(StatusCode::NOT_FOUND) => Error::NotFound(message),
+            Some(StatusCode::CONFLICT) => Error::Conflict(message),
+            _ if e.is_decode() => Error::Decode(message),
+            _ => Error::Network(message),
+        }
+    }
+
+    /// 根据 IO 错误的类型归类，`context` 说明正在进行的操作
+    pub fn io(context: &str, e: std::io::Error) -> Self {
+        let message = format!("{}: {}", context, e);
+        match e.kind() {
+            std::io::ErrorKind::NotFound => Error::NotFound(message),
+            std::io::ErrorKind::AlreadyExists => Error::Conflict(message),
+            _ => Error::Io(message),
+        }
+    }
+
+    /// 每类错误对应的进程退出码，1 与 2 分别留给一般失败与命令行参数错误
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Auth(_) => 3,
+            Error::NotFound(_) => 4,
+            Error::Conflict(_) => 5,
+            Error::Network(_) => 6,
+            Error::Decode(_) => 7,
+            Error::InvalidRules(_) => 8,
+            Error::Unsupported(_) => 9,
+            Error::Io(_) => 10,
+            Error::Config(_) => 2,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Auth(message)
+            | Error::NotFound(message)
+            | Error::Conflict(message)
+            | Error::Network(message)
+            | Error::Decode(message)
+            | Error::InvalidRules(message)
+            | Error::Unsupported(message)
+            | Error::Io(message)
+            | Error::Config(message) => f.write_str(message),
+        }
+    }
+}
+
+pub trait OrExit<T> {
+    /// 解包该值，如果失败，则记录错误消息并以错误类型对应的退出码退出
+    fn or_exit(self, msg: &str) -> T;
+}
+impl<T> OrExit<T> for Result<T, Error> {
+    fn or_exit(self, msg: &str) -> T {
+        match self {
+            Ok(val) => val,
+            Err(err) => {
+                crate::log!("{}: {}", msg, err);
+                std::process::exit(err.exit_code());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 退出码是脚本依赖的接口，与 README 中的退出码表保持一致
+    #[test]
+    fn test_2026_10_19_11_55_38() {
+        let codes = [
+            (Error::Config(String::new()), 2),
+            (Error::Auth(String::new()), 3),
+            (Error::NotFound(String::new()), 4),
+            (Error::Conflict(String::new()), 5),
+            (Error::Network(String::new()), 6),
+            (Error::Decode(String::new()), 7),
+            (Error::InvalidRules(String::new()), 8),
+            (Error::Unsupported(String::new()), 9),
+            (Error::Io(String::new()), 10),
+        ];
+        for (error, code) in codes {
+            assert_eq!(error.exit_code(), code, "{:?}", error);
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use crate::error::Error;
+use crate::plan::{Rename, RenameKind};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::{Mutex, OnceLock};
+use std::time::SystemTime;
+
+// 全局变量存储重命名日志文件路径
+static JOURNAL_FILE_PATH: OnceLock<Mutex<String>> = OnceLock::new();
+
+/// 重命名日志中的一条记录，每行一个 JSON 对象
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct JournalEntry {
+    /// RFC 3339 格式的 UTC 时间
+    pub timestamp: String,
+    /// 种子哈希值，`fs` 子命令中为目录的绝对路径
+    pub hash: String,
+    pub kind: RenameKind,
+    pub old: String,
+    pub new: String,
+}
+
+// 设置重命名日志文件路径
+pub fn set_journal_file(path: String) {
+    JOURNAL_FILE_PATH.get_or_init(|| Mutex::new(path));
+}
+
+/// 记录一次成功的重命名，未设置日志文件时不做任何事
+pub fn record(hash: &str, rename: &Rename) {
+    let Some(path) = JOURNAL_FILE_PATH.get() else { return };
+    let entry = JournalEntry {
+        timestamp: humantime::format_rfc3339_seconds(SystemTime::now()).to_string(),
+        hash: hash.to_string(),
+        kind: rename.kind,
+        old: rename.old.clone(),
+        new: rename.new.clone(),
+    };
+    let line = serde_json::to_string(&entry).unwrap();
+
+    // 持有锁期间写入，保证并发任务的记录各占一行
+    let path = path.lock().unwrap();
+    let result = OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(&*path)
+        .and_then(|mut file| writeln!(file, "{}", line));
+    if let Err(e) = result {
+        crate::log!("Failed to write journal {}: {}", path, e);
+    }
+}
+
+/// 读取重命名日志，按记录顺序返回。无法读取时为 `Io`，记录无法解析时为 `Decode`
+pub fn read(path: &str) -> Result<Vec<JournalEntry>, Error> {
+    let file = std::fs::File::open(path).map_err(|e| Error::Io(format!("Failed to open journal {}: {}", path, e)))?;
+    let mut entries = vec![];
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line.map_err(|e| Error::Io(format!("Failed to read journal {}: {}", path, e)))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry = serde_json::from_str(&line)
+            .map_err(|e| Error::Decode(format!("Invalid journal entry at {}:{}: {}", path, number + 1, e)))?;
+        entries.push(entry);
+    }
+    Ok(entries)
+}
+
+/// 解析 `--since` 参数，可以是 RFC 3339 时间，也可以是 `2h`、`30min` 这样的相对时长，无效时与其它参数错误相同
+pub fn parse_since(since: &str) -> Result<SystemTime, Error> {
+    humantime::parse_rfc3339_weak(since)
+        .or_else(|_| humantime::parse_duration(since).map(|d| SystemTime::now() - d))
+        .map_err(|_| Error::Config(format!("Invalid time {:?}, expected RFC 3339 time or a duration like '2h'", since)))
+}
+
+impl JournalEntry {
+    pub fn time(&self) -> Option<SystemTime> {
+        humantime::parse_rfc3339_weak(&self.timestamp).ok()
+    }
+
+    /// 撤销该记录所需的重命名操作
+    pub fn inverse(&self) -> Rename {
+        Rename { kind: self.kind, old: self.new.clone(), new: self.old.clone(), index: None }
+    }
+}
@@ -0,0 +1,129 @@
+use crate::client::{TorrentClient, TorrentFile, TorrentFilter, TorrentInfo};
+use crate::error::Error;
+use crate::plan::Rename;
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// 本地目录，不经过下载客户端直接在磁盘上重命名。
+/// 整个目录相当于一个种子，目录下的各级目录与文件相当于种子内的路径，目录本身不改名
+pub struct LocalFs {
+    root: PathBuf,
+    /// 有内容被移走的目录，所有重命名结束后删除其中变空的目录
+    vacated: Mutex<BTreeSet<PathBuf>>,
+}
+
+impl LocalFs {
+    pub fn new(root: &Path) -> Result<Self, Error> {
+        let root = root
+            .canonicalize()
+            .map_err(|e| Error::io(&root.display().to_string(), e))?;
+        if !root.is_dir() {
+            return Err(Error::NotFound(format!("Not a directory: {}", root.display())));
+        }
+        Ok(Self { root, vacated: Mutex::default() })
+    }
+
+    /// 代替种子哈希值标识该目录，写入重命名日志
+    pub fn id(&self) -> String {
+        self.root.display().to_string()
+    }
+
+    pub fn torrent(&self) -> TorrentInfo {
+        let name = self.root.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+        TorrentInfo { hash: self.id(), name }
+    }
+
+    /// 递归收集 `dir` 下的文件及其大小，路径相对于根目录并以 `/` 分隔；不进入指向目录的符号链接
+    fn walk(&self, dir: &Path, prefix: &str, files: &mut Vec<(String, u64)>) -> Result<(), Error> {
+        let context = || format!("Failed to read directory {}", dir.display());
+        for entry in std::fs::read_dir(dir).map_err(|e| Error::io(&context(), e))? {
+            let entry = entry.map_err(|e| Error::io(&context(), e))?;
+            let Some(name) = entry.file_name().to_str().map(str::to_string) else {
+                crate::log!("Skipping non UTF-8 path: {}", entry.path().display());
+                continue;
+            };
+            let path = format!("{}{}", prefix, name);
+            let metadata = entry.metadata().map_err(|e| Error::io(&context(), e))?;
+            if metadata.is_dir() {
+                self.walk(&entry.path(), &format!("{}/", path), files)?;
+            } else {
+                files.push((path, metadata.len()));
+            }
+        }
+        Ok(())
+    }
+}
+
+impl TorrentClient for LocalFs {
+    async fn list_torrents(&self, _filter: &TorrentFilter) -> Result<Vec<TorrentInfo>, Error> {
+        Ok(vec![self.torrent()])
+    }
+
+    async fn get_files(&self, _hash: &str) -> Result<Vec<TorrentFile>, Error> {
+        let mut files = vec![];
+        self.walk(&self.root, "", &mut files)?;
+        if files.is_empty() {
+            return Err(Error::NotFound(format!("No files found under {}", self.root.display())));
+        }
+        // 按路径排序，使计划与冲突的先后顺序稳定
+        files.sort();
+        Ok(files.into_iter().zip(0..).map(|((name, size), index)| TorrentFile { name, index, size }).collect())
+    }
+
+    /// 根目录是用户指定的目录，不随规则改名
+    fn supports_torrent_rename(&self) -> bool {
+        false
+    }
+
+    async fn rename_torrent(&self, _hash: &str, _name: &str) -> Result<(), Error> {
+        Err(Error::Unsupported("The root directory is never renamed".to_string()))
+    }
+
+    async fn rename_path(&self, _hash: &str, rename: &Rename, is_dir: bool) -> Result<(), Error> {
+        let (old, new) = (self.root.join(&rename.old), self.root.join(&rename.new));
+        // rename 会覆盖已存在的文件，这里先检查；只有大小写不同时目标即为自身，在大小写不敏感的文件系统上同样存在
+        if !rename.old.eq_ignore_ascii_case(&rename.new) && tokio::fs::symlink_metadata(&new).await.is_ok() {
+            return Err(Error::Conflict(format!("Target already exists: {}", new.display())));
+        }
+        let context = || format!("Failed to rename {} -> {}", old.display(), new.display());
+        // 按预设整理时文件会移动到新的目录下
+        if let Some(parent) = new.parent() {
+            tokio::fs::create_dir_all(parent).await.map_err(|e| Error::io(&context(), e))?;
+        }
+        tokio::fs::rename(&old, &new).await.map_err(|e| Error::io(&context(), e))?;
+        let mut vacated = self.vacated.lock().unwrap();
+        // 已记录的目录随改名的目录一起移动
+        if is_dir {
+            *vacated = vacated
+                .iter()
+                .map(|dir| dir.strip_prefix(&old).map_or_else(|_| dir.clone(), |rest| new.join(rest)))
+                .collect();
+        }
+        if let Some(parent) = old.parent() {
+            vacated.insert(parent.to_path_buf());
+        }
+        Ok(())
+    }
+
+    /// 移走后留下的空目录在所有重命名结束后一并删除，由深到浅，不删除根目录；
+    /// 放在最后进行，避免删除其它重命名正要移入的目录
+    async fn finish_renames(&self, _hash: &str) {
+        let vacated = std::mem::take(&mut *self.vacated.lock().unwrap());
+        let mut dirs: Vec<&Path> = vacated
+            .iter()
+            .flat_map(|dir| dir.ancestors().take_while(|dir| *dir != self.root && dir.starts_with(&self.root)))
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .collect();
+        dirs.sort_by_key(|dir| std::cmp::Reverse(dir.components().count()));
+        for dir in dirs {
+            // 仍有内容的目录删除失败，保留即可
+            let _ = tokio::fs::remove_dir(dir).await;
+        }
+    }
+
+    async fn add_tags(&self, _hash: &str, _tags: &[String]) -> Result<(), Error> {
+        Err(Error::Unsupported("Local directories have no tags".to_string()))
+    }
+}
@@ -51,8 +51,8 @@ macro_rules! log {
 
 // 实际的日志记录函数
 pub fn log_message(message: &str) {
-    // 打印到控制台
-    println!("{}", message);
+    // 打印到标准错误，标准输出留给重命名计划等结果
+    eprintln!("{}", message);
 
     // 尝试获取日志文件路径
     if let Some(log_file_mutex) = LOG_FILE_PATH.get() {
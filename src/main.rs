@@ -1,81 +1,430 @@
+mod bencode;
+mod client;
+mod config;
+mod deluge;
+mod error;
+mod journal;
+mod local;
 mod logger;
+mod metainfo;
+mod plan;
 mod q_bit;
 mod re;
+mod retry;
+mod rules;
+mod secret;
+mod tester;
+mod tidy;
+mod transmission;
+mod undo;
+mod watch;
 
+use crate::error::{Error, OrExit};
+use crate::local::LocalFs;
 use crate::logger::LogUnwrap;
-use crate::re::CompoundReplacer;
-use clap::Parser;
+use crate::plan::{ConflictStrategy, OutputFormat};
+use crate::client::{Backend, ClientKind, TorrentClient, TorrentFilter};
+use crate::deluge::Deluge;
+use crate::q_bit::QBittorrent;
+use crate::re::{Preset, Renamer, Rule, RuleMode, Scope, Template};
+use crate::secret::Secret;
+use crate::tidy::{Summary, Tidy};
+use crate::transmission::Transmission;
+use clap::{ArgGroup, Parser, Subcommand};
 use reqwest::Client;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tokio::task::JoinSet;
 
 #[derive(Parser, Debug)]
-#[command(version, about, long_about = None)]
+#[command(version, about, long_about = None, subcommand_negates_reqs = true)]
+#[command(group(ArgGroup::new("target").required(true).multiple(true).args(["torrent_hash", "all", "category", "tag", "filter"])))]
 struct Args {
-    #[arg(short, long, required=true, value_name = "URL", help = "URL of the qBittorrent WebUI")]
-    webui_url: String,
-    #[arg(short, long, required=true, value_name = "HASH", help = "Hash of the torrent to rename")]
-    torrent_hash: String,
-    #[arg(short, long, required=true, value_name = "PATTERN=REPLACEMENT", help = "Rename rules in the format 'pattern=replacement',or points to a file one rule for every two lines")]
-    rename_rules: Vec<String>,
-    #[arg(short, long, required=false, value_name = "USERNAME", help = "Username for qBittorrent WebUI authentication")]
-    username: Option<String>,
-    #[arg(short, long, required=false, value_name = "PASSWORD", help = "Password for qBittorrent WebUI authentication")]
-    password: Option<String>,
-    #[arg(short, long, required=false, help = "Use VPN for the request")]
-    vpn: bool,
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(long, global = true, value_name = "FILE", help = "Config file with named profiles [default: ~/.config/torrent-tidy/config.toml]")]
+    config: Option<String>,
+    #[arg(long, global = true, value_name = "NAME", help = "Fill in options not given on the command line from this profile of the config file")]
+    profile: Option<String>,
+    #[command(flatten)]
+    webui: WebUiArgs,
+    #[arg(short, long, value_name = "HASH", help = "Hash of a torrent to rename, can be repeated")]
+    torrent_hash: Vec<String>,
+    #[arg(long, help = "Rename every torrent, narrowed by '--category', '--tag' and '--filter' if given")]
+    all: bool,
+    #[arg(long, value_name = "CATEGORY", help = "Only rename torrents in this category, an empty value selects uncategorized torrents")]
+    category: Option<String>,
+    #[arg(long, value_name = "TAG", help = "Only rename torrents with this tag")]
+    tag: Option<String>,
+    #[arg(long, value_name = "STATE", help = "Only rename torrents in this qBittorrent state, e.g. downloading, seeding, completed, stopped, active, inactive, stalled, errored")]
+    filter: Option<String>,
+    #[arg(long, value_name = "N", default_value_t = 4, value_parser = clap::value_parser!(u16).range(1..), help = "Number of torrents processed at the same time")]
+    jobs: u16,
+    #[command(flatten)]
+    rules: RuleArgs,
     #[arg(short, long, required=false, value_name = "LOG_FILE_PATH", help = "Path to the log file")]
     log: Option<String>,
+    #[arg(short, long, required=false, value_name = "JOURNAL_FILE_PATH", help = "Append every applied rename to this JSON Lines journal, used by the 'undo' subcommand")]
+    journal: Option<String>,
+    #[arg(short = 'n', long, required=false, help = "Print the planned renames without sending any rename request, exits with the conflict exit code if the plan has conflicts")]
+    dry_run: bool,
+    #[arg(short, long, value_enum, default_value_t = OutputFormat::Table, help = "Output format of the dry-run plan")]
+    output: OutputFormat,
+    #[arg(short = 'c', long, value_enum, default_value_t = ConflictStrategy::Skip, help = "How to resolve renames that collide with each other or with existing files")]
+    on_conflict: ConflictStrategy,
+    #[arg(long, value_name = "DURATION", default_value = "5m", value_parser = humantime::parse_duration, help = "How long to wait for the metadata of a magnet link given with '-t' before giving up, 0 to not wait; magnet links selected by '--all', '--category', '--tag' or '--filter' are skipped until their metadata arrives")]
+    metadata_timeout: Duration,
+    #[arg(long, value_name = "TAG", help = "Add this tag (a label in Transmission and Deluge) to every torrent renamed without errors")]
+    mark_tag: Option<String>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    #[command(about = "Run rename rules over names read from stdin or a file, without any WebUI")]
+    TestRules {
+        #[command(flatten)]
+        rules: RuleArgs,
+        #[arg(short, long, value_name = "FILE", help = "File with one name per line, reads stdin if omitted")]
+        input: Option<String>,
+    },
+    #[command(about = "Revert renames recorded in a journal, newest first")]
+    Undo {
+        #[command(flatten)]
+        webui: WebUiArgs,
+        #[arg(short, long, required=true, value_name = "JOURNAL_FILE_PATH", help = "Journal written by '--journal'")]
+        journal: String,
+        #[arg(long, value_name = "HASH", required_unless_present = "since", help = "Only revert renames of this torrent")]
+        hash: Option<String>,
+        #[arg(long, value_name = "TIME", help = "Only revert renames at or after this time, RFC 3339 (e.g. 2025-03-01T12:00:00Z) or a duration ago (e.g. 2h)")]
+        since: Option<String>,
+    },
+    #[command(about = "Rename the folders and files under a local directory on disk, without any torrent client")]
+    Fs {
+        #[arg(value_name = "PATH", help = "Directory to tidy, the directory itself keeps its name")]
+        path: PathBuf,
+        // 与 RuleArgs 相同，但撤销时不需要规则
+        #[arg(short, long, required_unless_present_any = ["undo", "template", "preset"], value_name = "PATTERN=REPLACEMENT", help = "Rename rules in the format 'pattern=replacement', or points to a file one rule for every two lines, or a .toml rules file (optionally 'file.toml#group')")]
+        rename_rules: Vec<String>,
+        #[arg(short = 'm', long, value_enum, default_value_t = RuleMode::Compound, help = "How rename rules are applied: 'compound' matches all rules in one pass, 'sequential' runs each rule over the previous rule's output")]
+        rule_mode: RuleMode,
+        #[command(flatten)]
+        release: ReleaseArgs,
+        #[arg(short, long, required=false, value_name = "LOG_FILE_PATH", help = "Path to the log file")]
+        log: Option<String>,
+        #[arg(short, long, required=false, value_name = "JOURNAL_FILE_PATH", help = "Append every applied rename to this JSON Lines journal, used by '--undo'")]
+        journal: Option<String>,
+        #[arg(short = 'n', long, required=false, help = "Print the planned renames without touching the disk, exits with the conflict exit code if the plan has conflicts")]
+        dry_run: bool,
+        #[arg(short, long, value_enum, default_value_t = OutputFormat::Table, help = "Output format of the dry-run plan")]
+        output: OutputFormat,
+        #[arg(short = 'c', long, value_enum, default_value_t = ConflictStrategy::Skip, help = "How to resolve renames that collide with each other or with existing files")]
+        on_conflict: ConflictStrategy,
+        #[arg(long, requires = "journal", conflicts_with = "dry_run", help = "Revert the renames of this directory recorded in the journal, newest first, instead of renaming")]
+        undo: bool,
+        #[arg(long, value_name = "TIME", requires = "undo", help = "Only revert renames at or after this time, RFC 3339 (e.g. 2025-03-01T12:00:00Z) or a duration ago (e.g. 2h)")]
+        since: Option<String>,
+    },
+    #[command(about = "Rewrite the names inside a .torrent file before adding it to a client")]
+    TorrentFile {
+        #[arg(value_name = "TORRENT", help = "The .torrent file to read")]
+        input: PathBuf,
+        #[arg(short, long, value_name = "FILE", required_unless_present_any = ["dry_run", "fastresume"], help = "Where to write the rewritten .torrent, or the fastresume with '--fastresume' (default: <infohash>.fastresume next to the input)")]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        rules: RuleArgs,
+        #[arg(short = 'n', long, required=false, help = "Print the planned renames without writing anything, exits with the conflict exit code if the plan has conflicts")]
+        dry_run: bool,
+        #[arg(short = 'c', long, value_enum, default_value_t = ConflictStrategy::Skip, help = "How to resolve renames that collide with each other")]
+        on_conflict: ConflictStrategy,
+        #[arg(long, help = "Keep the .torrent and its infohash untouched and write the new names into a qBittorrent fastresume file instead")]
+        fastresume: bool,
+    },
+    #[command(about = "Keep running and rename torrents as soon as they are added or their metadata arrives")]
+    Watch {
+        #[command(flatten)]
+        webui: WebUiArgs,
+        #[command(flatten)]
+        rules: RuleArgs,
+        #[arg(short, long, required=false, value_name = "LOG_FILE_PATH", help = "Path to the log file")]
+        log: Option<String>,
+        #[arg(short, long, required=false, value_name = "JOURNAL_FILE_PATH", help = "Append every applied rename to this JSON Lines journal, used by the 'undo' subcommand")]
+        journal: Option<String>,
+        #[arg(short = 'c', long, value_enum, default_value_t = ConflictStrategy::Skip, help = "How to resolve renames that collide with each other or with existing files")]
+        on_conflict: ConflictStrategy,
+        #[arg(short, long, value_name = "DURATION", default_value = "2s", value_parser = humantime::parse_duration, help = "How often to poll qBittorrent for changes")]
+        interval: Duration,
+        #[arg(long, value_name = "DURATION", default_value = "5m", value_parser = humantime::parse_duration, help = "How long to wait for the metadata of a magnet link before giving up, 0 to not wait")]
+        metadata_timeout: Duration,
+        #[arg(long, value_name = "TAG", help = "Add this tag to every torrent renamed without errors")]
+        mark_tag: Option<String>,
+    },
+}
+
+#[derive(clap::Args, Debug)]
+struct WebUiArgs {
+    #[arg(short, long, required=true, value_name = "URL", help = "URL of the qBittorrent WebUI, of the Transmission web interface or RPC endpoint, or of the Deluge web interface")]
+    webui_url: Option<String>,
+    #[arg(long, value_enum, default_value_t = ClientKind::QBittorrent, help = "Torrent client behind the URL")]
+    client: ClientKind,
+    #[arg(short, long, required=false, value_name = "USERNAME", help = "Username for qBittorrent WebUI authentication [env: TORRENT_TIDY_USERNAME]")]
+    username: Option<Secret>,
+    #[arg(long, value_name = "FILE", conflicts_with = "username_stdin", help = "Read the username from the first line of this file, takes precedence over '--username'")]
+    username_file: Option<PathBuf>,
+    #[arg(long, help = "Read the username from a line of stdin, before the password if both are read from stdin")]
+    username_stdin: bool,
+    #[arg(short, long, required=false, value_name = "PASSWORD", help = "Password for qBittorrent WebUI authentication, or the Deluge web interface password; visible to other users in the process list, prefer the alternatives [env: TORRENT_TIDY_PASSWORD]")]
+    password: Option<Secret>,
+    #[arg(long, value_name = "FILE", conflicts_with = "password_stdin", help = "Read the password from the first line of this file, takes precedence over '--password'")]
+    password_file: Option<PathBuf>,
+    #[arg(long, help = "Read the password from a line of stdin")]
+    password_stdin: bool,
+    #[arg(short, long, required=false, help = "Use VPN for the request")]
+    vpn: bool,
+    #[arg(long, value_name = "N", default_value_t = 3, help = "Retries of a WebUI request after a connection error, timeout, 429 or 5xx, with jittered exponential backoff")]
+    retries: u32,
+    #[arg(long, value_name = "DURATION", default_value = "30s", value_parser = humantime::parse_duration, help = "Timeout of each WebUI request")]
+    request_timeout: Duration,
+    #[arg(long, value_name = "N", default_value_t = 8, value_parser = clap::value_parser!(u16).range(1..), help = "Maximum number of rename requests in flight, shared by torrent, folder and file renames of all torrents")]
+    max_concurrency: u16,
+}
+
+#[derive(clap::Args, Debug)]
+struct RuleArgs {
+    #[arg(short, long, required_unless_present_any = ["template", "preset"], value_name = "PATTERN=REPLACEMENT", help = "Rename rules in the format 'pattern=replacement', or points to a file one rule for every two lines, or a .toml rules file (optionally 'file.toml#group')")]
+    rename_rules: Vec<String>,
+    #[arg(short = 'm', long, value_enum, default_value_t = RuleMode::Compound, help = "How rename rules are applied: 'compound' matches all rules in one pass, 'sequential' runs each rule over the previous rule's output")]
+    rule_mode: RuleMode,
+    #[command(flatten)]
+    release: ReleaseArgs,
+}
+
+impl RuleArgs {
+    /// 加载规则并编译，失败时退出
+    fn load(&self) -> (Vec<Rule>, Renamer) {
+        let rules = rules::load_rules(&self.rename_rules).or_exit("Failed to load rename rules");
+        let renamer = self.release.apply(Renamer::new(&rules, self.rule_mode));
+        (rules, renamer)
+    }
+}
+
+#[derive(clap::Args, Debug)]
+struct ReleaseArgs {
+    #[arg(long, value_name = "TEMPLATE", help = "Parse names as release names after the rename rules and rebuild them from this template, e.g. '{title} ({year}) [{resolution} {source}]'. Fields: title, year, season, episode, se, resolution, source, codec, audio, hdr, group")]
+    template: Option<String>,
+    #[arg(long, value_enum, value_delimiter = ',', default_values_t = [Scope::Name, Scope::TopFolder, Scope::Stem], help = "Which parts of a name the template rebuilds")]
+    template_scope: Vec<Scope>,
+    #[arg(long, value_enum, conflicts_with = "template", help = "Lay out video and subtitle files the way Plex and Jellyfin expect, parsing names as release names after the rename rules: 'movie' as 'Title (Year)/Title (Year).mkv', 'tv' as 'Show/Season 01/Show - S01E02.mkv', 'auto' picks per file")]
+    preset: Option<Preset>,
+}
+
+impl ReleaseArgs {
+    /// 指定了模板或预设时附加到重命名器上，模板无效时退出
+    fn apply(&self, renamer: Renamer) -> Renamer {
+        if let Some(preset) = self.preset {
+            return renamer.with_preset(preset);
+        }
+        match &self.template {
+            Some(template) => {
+                let template = Template::new(template).map_err(Error::InvalidRules).or_exit("Invalid template");
+                renamer.with_template(template, &self.template_scope)
+            }
+            None => renamer,
+        }
+    }
 }
 
 #[tokio::main]
 async fn main() {
     // 解析命令行参数
-    let args = Args::parse();
+    let args = config::parse().or_exit("Failed to load config");
+    match args.command {
+        Some(Command::TestRules { rules: rule_args, input }) => {
+            let (rules, renamer) = rule_args.load();
+            let result = match input {
+                Some(path) => std::fs::File::open(path).and_then(|f| tester::run(&rules, &renamer, f)),
+                None => tester::run(&rules, &renamer, std::io::stdin()),
+            };
+            result.log_unwrap("Failed to read names");
+            return;
+        }
+        Some(Command::Undo { webui, journal, hash, since }) => {
+            let since = since.map(|s| journal::parse_since(&s).or_exit("Invalid --since"));
+            let entries = journal::read(&journal).or_exit("Failed to read journal");
+            let client = connect(webui).await;
+            if let Err(e) = undo::run(client, entries, hash.as_deref(), since).await {
+                std::process::exit(e.exit_code());
+            }
+            return;
+        }
+        Some(Command::Fs { path, rename_rules, rule_mode, release, log, journal, dry_run, output, on_conflict, undo, since }) => {
+            if let Some(log_path) = log { logger::set_log_file(log_path) }
+            let local = LocalFs::new(&path).or_exit("Failed to open directory");
+            if undo {
+                // 撤销时不写入重命名日志，避免撤销记录本身被再次撤销
+                let since = since.map(|s| journal::parse_since(&s).or_exit("Invalid --since"));
+                let entries = journal::read(&journal.unwrap()).or_exit("Failed to read journal");
+                let id = local.id();
+                let client = &*Box::leak(Box::new(Backend::Local(local)));
+                if let Err(e) = undo::run(client, entries, Some(&id), since).await {
+                    std::process::exit(e.exit_code());
+                }
+                return;
+            }
+            if let Some(journal_path) = journal { journal::set_journal_file(journal_path) }
+            let rules = rules::load_rules(&rename_rules).or_exit("Failed to load rename rules");
+            let torrent = local.torrent();
+            let tidy = Tidy {
+                client: Box::leak(Box::new(Backend::Local(local))),
+                renamer: release.apply(Renamer::new(&rules, rule_mode)),
+                on_conflict,
+                dry_run,
+                output,
+                metadata_timeout: Duration::ZERO,
+                mark_tag: None,
+            };
+            let summary = tidy.process(torrent, false).await;
+            report(&[summary], dry_run);
+            return;
+        }
+        Some(Command::TorrentFile { input, output, rules: rule_args, dry_run, on_conflict, fastresume }) => {
+            let (_, renamer) = rule_args.load();
+            metainfo::run(&input, output, &renamer, on_conflict, dry_run, fastresume).or_exit("Failed to tidy torrent file");
+            return;
+        }
+        Some(Command::Watch { webui, rules: rule_args, log, journal, on_conflict, interval, metadata_timeout, mark_tag }) => {
+            if let Some(log_path) = log { logger::set_log_file(log_path) }
+            if let Some(journal_path) = journal { journal::set_journal_file(journal_path) }
+            let (_, renamer) = rule_args.load();
+            let client = connect(webui).await;
+            // 增量同步接口只有 qBittorrent 提供
+            let Backend::QBittorrent(qbit) = client else {
+                let e = Error::Unsupported(format!("Watch mode is not supported for {}", client.name()));
+                log!("Failed to start watching: {}", e);
+                std::process::exit(e.exit_code());
+            };
+            let tidy = &*Box::leak(Box::new(Tidy {
+                client,
+                renamer,
+                on_conflict,
+                dry_run: false,
+                output: OutputFormat::Table,
+                metadata_timeout,
+                mark_tag,
+            }));
+            watch::run(tidy, qbit, interval).await;
+            return;
+        }
+        None => {}
+    }
     // 配置日志输出
     if let Some(log_path) = args.log { logger::set_log_file(log_path) }
+    if let Some(journal_path) = args.journal { journal::set_journal_file(journal_path) }
     // 提取参数 重命名规则，提前编译正则表达式
-    let mut rules = vec![];
-    for rule in args.rename_rules {
-        if let Some((p,r)) = rule.rsplit_once('=') {
-            rules.push((p.to_string(), r.to_string()));
-        } else {
-            // 如果没有等号，则认为是文件路径
-            let content = std::fs::read_to_string(&rule)
-                .log_unwrap("Failed to read rename rules file");
-            rules.extend(
-                content.lines()
-                    .collect::<Vec<_>>()
-                    .chunks_exact(2)
-                    .map(|c| (c[0].to_string(), c[1].to_string()))
-            );
+    let (_, renamer) = args.rules.load();
+
+    let client = connect(args.webui).await;
+    let filter = TorrentFilter {
+        hashes: args.torrent_hash,
+        category: args.category,
+        tag: args.tag,
+        state: args.filter,
+    };
+    let torrents = client.list_torrents(&filter).await.or_exit("Failed to list torrents");
+    // 显式指定但不存在的 hash 各记为一个未找到的结果，其余种子照常处理
+    let missing: Vec<Summary> = filter
+        .hashes
+        .iter()
+        .filter(|hash| !torrents.iter().any(|t| t.hash.eq_ignore_ascii_case(hash)))
+        .map(|hash| {
+            log!("No torrent found with hash: {}", hash);
+            Summary::not_found(hash)
+        })
+        .collect();
+    let tidy = &*Box::leak(Box::new(Tidy {
+        client,
+        renamer,
+        on_conflict: args.on_conflict,
+        dry_run: args.dry_run,
+        output: args.output,
+        metadata_timeout: args.metadata_timeout,
+        mark_tag: args.mark_tag,
+    }));
+
+    // 并发处理各个种子，同时处理的数量不超过 --jobs
+    let semaphore = Arc::new(Semaphore::new(args.jobs as usize));
+    let mut tasks = JoinSet::new();
+    for (i, torrent) in torrents.into_iter().enumerate() {
+        let semaphore = semaphore.clone();
+        // 只等待显式指定的种子的元数据，按条件批量处理时跳过还没有元数据的磁力链接，避免失效的链接拖慢整批
+        let wait = filter.hashes.iter().any(|hash| hash.eq_ignore_ascii_case(&torrent.hash));
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await.unwrap();
+            (i, tidy.process(torrent, wait).await)
+        });
+    }
+    let mut summaries = vec![];
+    while let Some(res) = tasks.join_next().await {
+        match res {
+            Ok(summary) => summaries.push(summary),
+            Err(e) => log!("Task execution failed: {}", e),
         }
     }
-    
-    let mut builder = Client::builder().cookie_store(true);
-    if !args.vpn { builder = builder.no_proxy(); }
-    let client = Box::leak(Box::new(builder.build().unwrap()));
-    let webui_url = Box::leak(Box::new(args.webui_url));
-    let torrent_hash = Box::leak(Box::new(args.torrent_hash));
-    let compound_replacer = Box::leak(Box::new(CompoundReplacer::new(rules)));
 
-    // 如果提供了用户名和密码，则进行认证
-    if let (Some(u), Some(p)) = (args.username, args.password) {
-        q_bit::authenticate(client, webui_url, &u, &p)
-            .await
-            .log_unwrap("Failed to authenticate with qBittorrent WebUI");
-    } else {
-        log!("Skipping authentication as username and/or password were not provided.");
+    // 按种子列表的顺序输出汇总
+    summaries.sort_by_key(|(i, _)| *i);
+    let summaries: Vec<Summary> = summaries.into_iter().map(|(_, summary)| summary).chain(missing).collect();
+    report(&summaries, args.dry_run);
+}
+
+/// 输出每个种子的汇总，以第一个出错的种子对应的错误类型作为退出码，试运行时计划中的冲突同样视为冲突错误
+fn report(summaries: &[Summary], dry_run: bool) {
+    log!("Summary of {} torrents:", summaries.len());
+    for summary in summaries {
+        log!("  {}", summary);
+    }
+    if let Some(e) = summaries.iter().find_map(|s| s.first_error()) {
+        std::process::exit(e.exit_code());
     }
+    if dry_run && summaries.iter().any(|s| s.conflicts > 0) {
+        std::process::exit(Error::Conflict(String::new()).exit_code());
+    }
+}
 
-    let mut tasks = JoinSet::new();
-    tasks.spawn(q_bit::rename_torrent(client, webui_url, torrent_hash, compound_replacer));
-    tasks.spawn(q_bit::rename_files(client, webui_url, torrent_hash, compound_replacer));
+/// 创建所选下载客户端，qBittorrent 在提供了用户名和密码时先登录 WebUI，Deluge 在提供了密码时先登录 Web 界面
+async fn connect(args: WebUiArgs) -> &'static Backend {
+    let webui_url = args.webui_url.unwrap();
+    retry::set_max_retries(args.retries);
+    client::set_max_concurrency(args.max_concurrency as usize);
+    let mut builder = Client::builder()
+        .cookie_store(true)
+        .timeout(args.request_timeout)
+        .default_headers(q_bit::csrf_headers(&webui_url));
+    if !args.vpn { builder = builder.no_proxy(); }
+    let http = builder.build().unwrap();
+    // 用户名先于密码读取，两者都从标准输入读取时依次各读一行；命令行与配置都没有给出时使用环境变量
+    let env = |name: &str| std::env::var(name).ok();
+    let username = secret::resolve(args.username, args.username_file.as_deref(), args.username_stdin, env(secret::USERNAME_ENV), "username")
+        .or_exit("Failed to read username");
+    let password = secret::resolve(args.password, args.password_file.as_deref(), args.password_stdin, env(secret::PASSWORD_ENV), "password")
+        .or_exit("Failed to read password")
+        .map(Secret::into_inner);
+    let credentials = username.map(Secret::into_inner).zip(password.clone());
 
-    while let Some(res) = tasks.join_next().await {
-        match res { 
-            Ok(Ok(_)) => {},
-            Ok(Err(e)) => log!("Task failed: {:?}", e),
-            Err(_) => log!("Task failed"),
+    let backend = match args.client {
+        ClientKind::QBittorrent => {
+            let qbit = QBittorrent::new(http, webui_url, credentials);
+            qbit.login().await.or_exit("Failed to authenticate with qBittorrent WebUI");
+            Backend::QBittorrent(qbit)
         }
-    }
-}
\ No newline at end of file
+        ClientKind::Transmission => Backend::Transmission(Transmission::new(http, webui_url, credentials)),
+        // Deluge Web 只有密码
+        ClientKind::Deluge => {
+            let deluge = Deluge::new(http, webui_url, password);
+            deluge.login().await.or_exit("Failed to authenticate with Deluge Web");
+            Backend::Deluge(deluge)
+        }
+    };
+    Box::leak(Box::new(backend))
+}
@@ -0,0 +1,285 @@
+use crate::bencode::{self, Value};
+use crate::client::{TorrentFile, TorrentInfo};
+use crate::error::Error;
+use crate::log;
+use crate::plan::{ConflictStrategy, OutputFormat, Rename, RenameKind, RenamePlan};
+use crate::re::{split_filename, Renamer};
+use std::path::{Path, PathBuf};
+
+/// 一个 .torrent 文件（BitTorrent v1 元信息）
+pub struct Metainfo {
+    root: Value,
+    /// 原始 info 字典的 SHA-1，按文件中的原始字节计算
+    info_hash: [u8; 20],
+}
+
+impl Metainfo {
+    pub fn load(path: &Path) -> Result<Self, Error> {
+        let data = std::fs::read(path).map_err(|e| Error::io(&path.display().to_string(), e))?;
+        let (root, span) = bencode::decode_with_span(&data, "info")
+            .map_err(|e| Error::Decode(format!("Invalid torrent file {}: {}", path.display(), e)))?;
+        let Some(span) = span.filter(|_| matches!(root.get("info"), Some(Value::Dict(_)))) else {
+            return Err(Error::Decode(format!("Torrent file {} has no info dictionary", path.display())));
+        };
+        // v2 的 file tree 以路径为键，且 infohash 为 SHA-256，暂不支持
+        if root.get("info").and_then(|info| info.get("file tree")).is_some() {
+            return Err(Error::Unsupported("BitTorrent v2 and hybrid torrents are not supported".to_string()));
+        }
+        Ok(Self { root, info_hash: sha1(&data[span]) })
+    }
+
+    fn info(&self) -> &Value {
+        self.root.get("info").unwrap()
+    }
+
+    fn info_mut(&mut self) -> &mut Value {
+        self.root.get_mut("info").unwrap()
+    }
+
+    pub fn info_hash(&self) -> String {
+        hex(&self.info_hash)
+    }
+
+    fn name(&self) -> Result<&str, Error> {
+        self.info().get("name").and_then(Value::as_str).ok_or_else(|| Error::Decode("Torrent has no UTF-8 name".to_string()))
+    }
+
+    pub fn torrent(&self) -> Result<TorrentInfo, Error> {
+        Ok(TorrentInfo { hash: self.info_hash(), name: self.name()?.to_string() })
+    }
+
+    /// 按规则生成重命名计划。单文件种子的名称就是文件名，与文件改名一样名称规则只作用于主名，扩展名保持不变
+    pub fn plan(&self, renamer: &Renamer, on_conflict: ConflictStrategy) -> Result<RenamePlan, Error> {
+        let torrent = self.torrent()?;
+        let mut plan = RenamePlan::new(&torrent, &self.files()?, renamer, on_conflict);
+        if self.info().get("files").is_some() || renamer.has_preset() {
+            return Ok(plan);
+        }
+        plan.renames.retain(|r| r.kind != RenameKind::Torrent);
+        let (stem, ext) = split_filename(&torrent.name);
+        let new_stem = renamer.rename_torrent(&stem);
+        let new_name = if ext.is_empty() { new_stem } else { format!("{}.{}", new_stem, ext) };
+        if new_name != torrent.name {
+            plan.renames.insert(0, Rename { kind: RenameKind::Torrent, old: torrent.name, new: new_name, index: None });
+        }
+        Ok(plan)
+    }
+
+    /// 种子内的文件，与 qBittorrent 一样多文件种子的路径以种子名称为顶层目录；
+    /// 对齐用的填充文件（BEP 47）不参与重命名
+    pub fn files(&self) -> Result<Vec<TorrentFile>, Error> {
+        let name = self.name()?;
+        let Some(files) = self.info().get("files") else {
+            let size = self.info().get("length").and_then(Value::as_int).unwrap_or_default();
+            return Ok(vec![TorrentFile { name: name.to_string(), index: 0, size }]);
+        };
+        let mut result = vec![];
+        for (index, file) in files.as_list().unwrap_or_default().iter().enumerate() {
+            if file.get("attr").and_then(Value::as_str).is_some_and(|attr| attr.contains('p')) {
+                continue;
+            }
+            let path = file_path(file).ok_or_else(|| Error::Decode(format!("File #{} has no UTF-8 path", index)))?;
+            let size = file.get("length").and_then(Value::as_int).unwrap_or_default();
+            result.push(TorrentFile { name: format!("{}/{}", name, path.join("/")), index: index as u32, size });
+        }
+        Ok(result)
+    }
+
+    /// 执行计划后每个文件的新路径，按文件序号排列，填充文件保持原路径
+    fn renamed_paths(&self, plan: &RenamePlan) -> Result<Vec<String>, Error> {
+        let name = self.name()?;
+        let mut paths: Vec<String> = match self.info().get("files").and_then(Value::as_list) {
+            Some(files) => files.iter().map(|f| format!("{}/{}", name, file_path(f).unwrap_or_default().join("/"))).collect(),
+            None => vec![name.to_string()],
+        };
+        // 目录由深到浅改名，其下的文件随之移动；文件改名时已位于新目录下
+        for rename in plan.folder_renames() {
+            let (old, new) = (format!("{}/", rename.old), format!("{}/", rename.new));
+            for path in paths.iter_mut() {
+                if let Some(rest) = path.strip_prefix(&old) {
+                    *path = format!("{}{}", new, rest);
+                }
+            }
+        }
+        // 文件按序号对应，不同文件改名前后的路径可能相同
+        for rename in plan.file_renames() {
+            if let Some(index) = rename.index && let Some(path) = paths.get_mut(index as usize) {
+                *path = rename.new.clone();
+            }
+        }
+        Ok(paths)
+    }
+
+    /// 把计划中的目录与文件改名写入 info 字典。多文件种子的名称就是顶层目录，单文件种子的名称就是文件名
+    pub fn apply(&mut self, plan: &RenamePlan) -> Result<(), Error> {
+        let paths = self.renamed_paths(plan)?;
+        let info = self.info_mut();
+        let name = match info.get_mut("files") {
+            Some(Value::List(files)) => {
+                let mut name = String::new();
+                for (file, path) in files.iter_mut().zip(&paths) {
+                    let (top, rest) = path.split_once('/').unwrap_or((path, ""));
+                    if !name.is_empty() && name != top {
+                        return Err(Error::Unsupported(format!("Files of a torrent file must share one top-level folder: {} and {}", name, top)));
+                    }
+                    name = top.to_string();
+                    let components = Value::List(rest.split('/').map(Value::string).collect());
+                    if file.get("path.utf-8").is_some() {
+                        file.insert("path.utf-8", components.clone());
+                    }
+                    file.insert("path", components);
+                }
+                name
+            }
+            // 单文件种子没有目录，文件不能移动到目录下
+            _ if paths[0].contains('/') => {
+                return Err(Error::Unsupported(format!("A single-file torrent file can not be moved into a folder: {}", paths[0])));
+            }
+            _ => paths[0].clone(),
+        };
+        if info.get("name.utf-8").is_some() {
+            info.insert("name.utf-8", Value::string(&name));
+        }
+        info.insert("name", Value::string(&name));
+        Ok(())
+    }
+
+    /// 修改后的 info 字典的 SHA-1
+    pub fn new_info_hash(&self) -> String {
+        hex(&sha1(&self.info().encode()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        std::fs::write(path, self.root.encode()).map_err(|e| Error::io(&path.display().to_string(), e))
+    }
+
+    /// 生成 qBittorrent（libtorrent）的 fastresume：`mapped_files` 记录每个文件的新路径，
+    /// `qBt-name` 记录种子的显示名称，.torrent 本身与 infohash 保持不变
+    pub fn fastresume(&self, plan: &RenamePlan) -> Result<Value, Error> {
+        let mut resume = Value::Dict(Default::default());
+        resume.insert("file-format", Value::string("libtorrent resume file"));
+        resume.insert("file-version", Value::Int(1));
+        resume.insert("info-hash", Value::Bytes(self.info_hash.to_vec()));
+        let paths = self.renamed_paths(plan)?;
+        resume.insert("mapped_files", Value::List(paths.iter().map(|p| Value::string(p)).collect()));
+        if let Some(rename) = plan.torrent_rename() {
+            resume.insert("qBt-name", Value::string(&rename.new));
+        }
+        Ok(resume)
+    }
+}
+
+/// 文件的路径分量，优先使用 `path.utf-8`
+fn file_path(file: &Value) -> Option<Vec<String>> {
+    let path = file.get("path.utf-8").or(file.get("path"))?.as_list()?;
+    path.iter().map(|c| c.as_str().map(str::to_string)).collect()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    sha1_smol::Sha1::from(data).digest().bytes()
+}
+
+/// `torrent-file` 子命令：按规则重写 .torrent 中的名称，或只生成 fastresume，并报告 infohash 的变化
+pub fn run(
+    input: &Path,
+    output: Option<PathBuf>,
+    renamer: &Renamer,
+    on_conflict: ConflictStrategy,
+    dry_run: bool,
+    fastresume: bool,
+) -> Result<(), Error> {
+    let mut metainfo = Metainfo::load(input)?;
+    let torrent = metainfo.torrent()?;
+    let plan = metainfo.plan(renamer, on_conflict)?;
+
+    if dry_run {
+        plan.print(OutputFormat::Table);
+        if !plan.conflicts.is_empty() {
+            return Err(Error::Conflict(format!("{} conflicts in the plan", plan.conflicts.len())));
+        }
+        return Ok(());
+    }
+    for conflict in &plan.conflicts {
+        log!("Conflict ({:?}): {} <- {}", plan.strategy, conflict.target, conflict.sources.join(", "));
+    }
+    if plan.strategy == ConflictStrategy::Abort && !plan.conflicts.is_empty() {
+        return Err(Error::Conflict("Aborted due to conflicts".to_string()));
+    }
+
+    if fastresume {
+        // qBittorrent 在 BT_backup 目录中按 <infohash>.fastresume 查找
+        let output = output.unwrap_or_else(|| input.with_file_name(format!("{}.fastresume", torrent.hash)));
+        let resume = metainfo.fastresume(&plan)?;
+        std::fs::write(&output, resume.encode()).map_err(|e| Error::io(&output.display().to_string(), e))?;
+        log!("Wrote {} renames to {}", plan.renames.len(), output.display());
+        log!("Infohash unchanged: {}", torrent.hash);
+        return Ok(());
+    }
+
+    // 独立的种子显示名称只存在于客户端中，.torrent 的名称随顶层目录或文件一起改变
+    if let Some(rename) = plan.torrent_rename() {
+        log!("Skipping torrent rename {} -> {}, a torrent file is named after its top-level folder or file", rename.old, rename.new);
+    }
+    metainfo.apply(&plan)?;
+    // 必需参数由命令行保证
+    let output = output.unwrap();
+    metainfo.save(&output)?;
+    log!("Wrote {} to {}", metainfo.name()?, output.display());
+    let new_hash = metainfo.new_info_hash();
+    if new_hash == torrent.hash {
+        log!("Infohash unchanged: {}", new_hash);
+    } else {
+        log!("Infohash changed: {} -> {}", torrent.hash, new_hash);
+        log!("Warning: the rewritten torrent is a different torrent to trackers and peers, use --fastresume to keep the original infohash");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::re::{Rule, RuleMode};
+
+    #[test]
+    fn test_2026_10_18_20_05_12() {
+        let data = b"d4:infod5:filesld6:lengthi1e4:pathl3:a.b5:c.mkveed4:attr1:p6:lengthi1e4:pathl4:.pad1:1eed6:lengthi1e4:pathl5:d.mkveee4:name5:T.a.b12:piece lengthi16384eee";
+        let (root, span) = bencode::decode_with_span(data, "info").unwrap();
+        let mut metainfo = Metainfo { root, info_hash: sha1(&data[span.unwrap()]) };
+        let files = metainfo.files().unwrap();
+        assert_eq!(files.iter().map(|f| (f.name.as_str(), f.index, f.size)).collect::<Vec<_>>(), [("T.a.b/a.b/c.mkv", 0, 1), ("T.a.b/d.mkv", 2, 1)]);
+
+        let renamer = Renamer::new(&[Rule::new(r"\.", " ")], RuleMode::Compound);
+        let plan = RenamePlan::new(&metainfo.torrent().unwrap(), &files, &renamer, ConflictStrategy::Skip);
+        let resume = metainfo.fastresume(&plan).unwrap();
+        let mapped: Vec<_> = resume.get("mapped_files").unwrap().as_list().unwrap().iter().map(|p| p.as_str().unwrap()).collect();
+        assert_eq!(mapped, ["T a b/a b/c.mkv", "T a b/.pad/1", "T a b/d.mkv"]);
+
+        let old_hash = metainfo.info_hash();
+        metainfo.apply(&plan).unwrap();
+        assert_eq!(metainfo.name().unwrap(), "T a b");
+        assert_eq!(file_path(&metainfo.info().get("files").unwrap().as_list().unwrap()[0]).unwrap(), ["a b", "c.mkv"]);
+        assert_ne!(metainfo.new_info_hash(), old_hash);
+    }
+
+    #[test]
+    fn test_2026_10_19_11_20_47() {
+        let data = b"d4:infod6:lengthi1e4:name9:A.B.C.mkv12:piece lengthi16384eee";
+        let (root, span) = bencode::decode_with_span(data, "info").unwrap();
+        let mut metainfo = Metainfo { root, info_hash: sha1(&data[span.unwrap()]) };
+        let renamer = Renamer::new(&[Rule::new(r"\.", " ")], RuleMode::Compound);
+        let plan = metainfo.plan(&renamer, ConflictStrategy::Skip).unwrap();
+        assert_eq!(plan.torrent_rename().unwrap().new, "A B C.mkv");
+
+        let resume = metainfo.fastresume(&plan).unwrap();
+        assert_eq!(resume.get("qBt-name").and_then(Value::as_str), Some("A B C.mkv"));
+        let mapped: Vec<_> = resume.get("mapped_files").unwrap().as_list().unwrap().iter().map(|p| p.as_str().unwrap()).collect();
+        assert_eq!(mapped, ["A B C.mkv"]);
+
+        metainfo.apply(&plan).unwrap();
+        assert_eq!(metainfo.name().unwrap(), "A B C.mkv");
+    }
+}
@@ -0,0 +1,527 @@
+use crate::client::{TorrentFile, TorrentInfo};
+use crate::re::{split_filename, Layout, Renamer};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fmt::Write;
+
+/// 重命名的对象类型
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RenameKind {
+    Torrent,
+    Folder,
+    File,
+}
+
+/// 一次重命名操作
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    pub kind: RenameKind,
+    pub old: String,
+    pub new: String,
+    /// 文件在种子中的序号，Deluge 按序号重命名文件；撤销记录中没有序号
+    #[serde(skip)]
+    pub index: Option<u32>,
+}
+
+/// 多个文件或目录改名后的最终路径相同，包括与不改名的已有文件或目录相同
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub target: String,
+    pub sources: Vec<String>,
+}
+
+/// 一个种子的重命名计划，计算过程不访问 WebUI
+#[derive(Serialize, Debug, Clone)]
+pub struct RenamePlan {
+    pub hash: String,
+    pub renames: Vec<Rename>,
+    /// 解决之前检测到的冲突
+    pub conflicts: Vec<Conflict>,
+    #[serde(rename = "on_conflict")]
+    pub strategy: ConflictStrategy,
+}
+
+/// 计划的输出格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Table,
+    /// JSON Lines，每个种子的计划占一行
+    Json,
+}
+
+/// 冲突的处理方式
+#[derive(Serialize, Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStrategy {
+    /// 保留已占用该路径的文件或第一个改名的文件，其余文件不改名
+    #[default]
+    Skip,
+    /// 其余文件在名称后追加 ` (2)`、` (3)` 等序号
+    Suffix,
+    /// 冲突涉及的所有文件都保留原名
+    Keep,
+    /// 放弃整个种子的重命名
+    Abort,
+}
+
+impl RenamePlan {
+    pub fn new(torrent: &TorrentInfo, files: &[TorrentFile], renamer: &Renamer, strategy: ConflictStrategy) -> Self {
+        if renamer.has_preset() {
+            return Self::layout(torrent, files, renamer, strategy);
+        }
+        let mut renames = vec![];
+
+        let new_name = renamer.rename_torrent(&torrent.name);
+        if new_name != torrent.name {
+            renames.push(Rename { kind: RenameKind::Torrent, old: torrent.name.clone(), new: new_name, index: None });
+        }
+
+        let mut tree = Tree::new(files, renamer);
+        let conflicts: Vec<Conflict> = tree
+            .conflicts()
+            .into_iter()
+            .map(|(target, sources)| Conflict { target, sources: sources.into_iter().map(|i| tree.old_path(i)).collect() })
+            .collect();
+
+        if strategy == ConflictStrategy::Abort && !conflicts.is_empty() {
+            return Self { hash: torrent.hash.clone(), renames: vec![], conflicts, strategy };
+        }
+        tree.resolve(strategy);
+        renames.extend(tree.renames());
+
+        Self { hash: torrent.hash.clone(), renames, conflicts, strategy }
+    }
+
+    /// 按媒体服务器预设重新安排文件。所有文件位于同一顶层目录、新路径的顶层目录也相同时，
+    /// 先整体改名顶层目录，其它文件随之移动；文件再直接改名到新路径，必要时移动到其它目录
+    fn layout(torrent: &TorrentInfo, files: &[TorrentFile], renamer: &Renamer, strategy: ConflictStrategy) -> Self {
+        let top = |path: &str| path.split_once('/').map(|(top, _)| top.to_string());
+        let layouts: Vec<Option<Layout>> = files
+            .iter()
+            .map(|f| renamer.layout_file(&f.name, top(&f.name).as_deref().unwrap_or(&torrent.name)))
+            .collect();
+        // 同一部电影的多个视频中只有最大的是正片，其余的（样片、花絮等）放到 Extras 目录下
+        let mut movies: BTreeMap<&str, Vec<usize>> = BTreeMap::new();
+        for (i, layout) in layouts.iter().enumerate() {
+            if let Some(layout) = layout && layout.extra.is_some() {
+                movies.entry(&layout.path).or_default().push(i);
+            }
+        }
+        let main: HashSet<usize> = movies
+            .into_values()
+            .filter_map(|videos| videos.into_iter().max_by_key(|&i| (files[i].size, std::cmp::Reverse(i))))
+            .collect();
+        let targets: Vec<Option<String>> = layouts
+            .into_iter()
+            .enumerate()
+            .map(|(i, layout)| layout.map(|l| if main.contains(&i) { l.path } else { l.extra.unwrap_or(l.path) }))
+            .collect();
+        let single = |tops: BTreeSet<Option<String>>| if tops.len() == 1 { tops.into_iter().next().flatten() } else { None };
+        let new_top = single(targets.iter().flatten().map(|t| top(t)).collect());
+
+        let mut renames = vec![];
+        if let Some(new_top) = &new_top && *new_top != torrent.name {
+            renames.push(Rename { kind: RenameKind::Torrent, old: torrent.name.clone(), new: new_top.clone(), index: None });
+        }
+        // 目录改名后各文件所在的路径
+        let mut bases: Vec<String> = files.iter().map(|f| f.name.clone()).collect();
+        if let (Some(old_top), Some(new_top)) = (single(files.iter().map(|f| top(&f.name)).collect()), &new_top)
+            && old_top != *new_top
+        {
+            renames.push(Rename { kind: RenameKind::Folder, old: old_top.clone(), new: new_top.clone(), index: None });
+            for base in bases.iter_mut() {
+                *base = format!("{}{}", new_top, &base[old_top.len()..]);
+            }
+        }
+
+        let mut paths: Vec<String> = targets.into_iter().zip(&bases).map(|(t, base)| t.unwrap_or_else(|| base.clone())).collect();
+        let conflicts: Vec<Conflict> = path_conflicts(&paths)
+            .into_iter()
+            .map(|(target, sources)| Conflict { target, sources: sources.into_iter().map(|i| files[i].name.clone()).collect() })
+            .collect();
+        if strategy == ConflictStrategy::Abort && !conflicts.is_empty() {
+            return Self { hash: torrent.hash.clone(), renames: vec![], conflicts, strategy };
+        }
+        // 与目录树的处理相同：保留已占用该路径或第一个文件，其余按策略调整，直到不再有冲突
+        loop {
+            let mut changed = false;
+            for sources in path_conflicts(&paths).into_values() {
+                let winner = sources.iter().position(|&i| paths[i] == bases[i]).unwrap_or(0);
+                for (k, &i) in sources.iter().enumerate() {
+                    if k == winner && strategy != ConflictStrategy::Keep {
+                        continue;
+                    }
+                    let new_path = match strategy {
+                        ConflictStrategy::Suffix => suffixed_path(&paths, i),
+                        _ => bases[i].clone(),
+                    };
+                    if new_path != paths[i] {
+                        paths[i] = new_path;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+
+        renames.extend(
+            bases
+                .into_iter()
+                .zip(paths)
+                .zip(files)
+                .filter(|((old, new), _)| old != new)
+                .map(|((old, new), file)| Rename { kind: RenameKind::File, old, new, index: Some(file.index) }),
+        );
+        Self { hash: torrent.hash.clone(), renames, conflicts, strategy }
+    }
+
+    pub fn torrent_rename(&self) -> Option<&Rename> {
+        self.renames.iter().find(|r| r.kind == RenameKind::Torrent)
+    }
+
+    /// 目录改名按由深到浅的顺序排列，需要依次执行
+    pub fn folder_renames(&self) -> impl Iterator<Item = &Rename> {
+        self.renames.iter().filter(|r| r.kind == RenameKind::Folder)
+    }
+
+    pub fn file_renames(&self) -> impl Iterator<Item = &Rename> {
+        self.renames.iter().filter(|r| r.kind == RenameKind::File)
+    }
+
+    /// 将计划打印到标准输出
+    pub fn print(&self, format: OutputFormat) {
+        // 先拼接完整输出再一次性打印，批量处理时多个种子的计划不会交错
+        let mut out = String::new();
+        match format {
+            OutputFormat::Json => out.push_str(&serde_json::to_string(self).unwrap()),
+            OutputFormat::Table => {
+                let _ = write!(out, "Torrent: {}", self.hash);
+                let old_width = self.renames.iter().map(|r| r.old.chars().count()).max().unwrap_or(0);
+                for rename in &self.renames {
+                    let kind = match rename.kind {
+                        RenameKind::Torrent => "torrent",
+                        RenameKind::Folder => "folder",
+                        RenameKind::File => "file",
+                    };
+                    let pad = old_width - rename.old.chars().count();
+                    let _ = write!(out, "\n  {:<7}  {}{}  ->  {}", kind, rename.old, " ".repeat(pad), rename.new);
+                }
+                if self.renames.is_empty() {
+                    out.push_str("\n  (nothing to rename)");
+                }
+                for conflict in &self.conflicts {
+                    let _ = write!(out, "\n  CONFLICT  {} <- {}", conflict.target, conflict.sources.join(", "));
+                }
+            }
+        }
+        println!("{}", out);
+    }
+}
+
+/// 相同的文件路径，按路径排列
+fn path_conflicts(paths: &[String]) -> BTreeMap<String, Vec<usize>> {
+    let mut targets: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (index, path) in paths.iter().enumerate() {
+        targets.entry(path.clone()).or_default().push(index);
+    }
+    targets.retain(|_, sources| sources.len() > 1);
+    targets
+}
+
+/// 在文件名的扩展名之前追加最小的未被占用的序号
+fn suffixed_path(paths: &[String], index: usize) -> String {
+    let (parent, name) = match paths[index].rsplit_once('/') {
+        Some((parent, name)) => (format!("{}/", parent), name),
+        None => (String::new(), paths[index].as_str()),
+    };
+    let (stem, ext) = split_filename(name);
+    (2..)
+        .map(|n| if ext.is_empty() { format!("{}{} ({})", parent, stem, n) } else { format!("{}{} ({}).{}", parent, stem, n, ext) })
+        .find(|path| !paths.contains(path))
+        .unwrap()
+}
+
+/// 种子内的目录或文件，目录按路径去重
+#[derive(Debug)]
+struct Node {
+    parent: Option<usize>,
+    depth: usize,
+    name: String,
+    new_name: String,
+    is_dir: bool,
+    /// 文件在种子中的序号，目录没有序号
+    index: Option<u32>,
+}
+
+/// 种子内的目录树，记录每一级目录与文件改名前后的名称
+#[derive(Debug)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    fn new(files: &[TorrentFile], renamer: &Renamer) -> Self {
+        let mut nodes: Vec<Node> = vec![];
+        let mut dirs: HashMap<String, usize> = HashMap::new();
+        for file in files {
+            let mut components: Vec<&str> = file.name.split('/').collect();
+            let name = components.pop().unwrap_or_default();
+
+            let mut parent = None;
+            for (depth, dir) in components.iter().enumerate() {
+                let path = components[..=depth].join("/");
+                let index = *dirs.entry(path).or_insert_with(|| {
+                    nodes.push(Node {
+                        parent,
+                        depth,
+                        name: dir.to_string(),
+                        new_name: renamer.rename_folder(dir, depth),
+                        is_dir: true,
+                        index: None,
+                    });
+                    nodes.len() - 1
+                });
+                parent = Some(index);
+            }
+            nodes.push(Node {
+                parent,
+                depth: components.len(),
+                name: name.to_string(),
+                new_name: renamer.rename_file(name),
+                is_dir: false,
+                index: Some(file.index),
+            });
+        }
+        Self { nodes }
+    }
+
+    fn path(&self, index: usize, new: bool) -> String {
+        let node = &self.nodes[index];
+        let name = if new { &node.new_name } else { &node.name };
+        match node.parent {
+            Some(parent) => format!("{}/{}", self.path(parent, new), name),
+            None => name.clone(),
+        }
+    }
+
+    fn old_path(&self, index: usize) -> String {
+        self.path(index, false)
+    }
+
+    fn new_path(&self, index: usize) -> String {
+        self.path(index, true)
+    }
+
+    /// 改名后的最终路径相同即为冲突，包括与不改名的文件或目录相同
+    fn conflicts(&self) -> BTreeMap<String, Vec<usize>> {
+        let mut targets: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+        for index in 0..self.nodes.len() {
+            targets.entry(self.new_path(index)).or_default().push(index);
+        }
+        targets.retain(|_, sources| sources.len() > 1);
+        targets
+    }
+
+    /// 按策略调整冲突节点的新名称，直到不再有冲突
+    fn resolve(&mut self, strategy: ConflictStrategy) {
+        loop {
+            let mut changed = false;
+            for sources in self.conflicts().into_values() {
+                // 保留已占用该路径的节点，否则保留第一个
+                let winner = sources
+                    .iter()
+                    .position(|&i| self.nodes[i].name == self.nodes[i].new_name)
+                    .unwrap_or(0);
+                for (k, &i) in sources.iter().enumerate() {
+                    if k == winner && strategy != ConflictStrategy::Keep {
+                        continue;
+                    }
+                    let new_name = match strategy {
+                        ConflictStrategy::Suffix => self.suffixed(i),
+                        _ => self.nodes[i].name.clone(),
+                    };
+                    if new_name != self.nodes[i].new_name {
+                        self.nodes[i].new_name = new_name;
+                        changed = true;
+                    }
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+    }
+
+    /// 在新名称后追加最小的未被占用的序号，文件的序号位于扩展名之前
+    fn suffixed(&self, index: usize) -> String {
+        let node = &self.nodes[index];
+        let (stem, ext) = if node.is_dir { (node.new_name.clone(), String::new()) } else { split_filename(&node.new_name) };
+        let taken: HashSet<String> = (0..self.nodes.len()).map(|i| self.new_path(i)).collect();
+        let parent = node.parent.map(|p| format!("{}/", self.new_path(p))).unwrap_or_default();
+        (2..)
+            .map(|n| if ext.is_empty() { format!("{} ({})", stem, n) } else { format!("{} ({}).{}", stem, n, ext) })
+            .find(|name| !taken.contains(&format!("{}{}", parent, name)))
+            .unwrap()
+    }
+
+    /// 生成重命名操作：目录由深到浅，改名时上级目录仍是旧名称；文件在目录改名后执行，位于新目录下
+    fn renames(&self) -> Vec<Rename> {
+        let changed = |node: &&Node| node.name != node.new_name;
+        let parent_path = |node: &Node, new: bool| node.parent.map(|p| format!("{}/", self.path(p, new))).unwrap_or_default();
+
+        let mut folders: Vec<(usize, Rename)> = self
+            .nodes
+            .iter()
+            .filter(|n| n.is_dir)
+            .filter(changed)
+            .map(|node| {
+                let parent = parent_path(node, false);
+                let rename = Rename {
+                    kind: RenameKind::Folder,
+                    old: format!("{}{}", parent, node.name),
+                    new: format!("{}{}", parent, node.new_name),
+                    index: None,
+                };
+                (node.depth, rename)
+            })
+            .collect();
+        folders.sort_by(|(a, x), (b, y)| b.cmp(a).then_with(|| x.old.cmp(&y.old)));
+
+        let files = self.nodes.iter().filter(|n| !n.is_dir).filter(changed).map(|node| {
+            let parent = parent_path(node, true);
+            Rename {
+                kind: RenameKind::File,
+                old: format!("{}{}", parent, node.name),
+                new: format!("{}{}", parent, node.new_name),
+                index: node.index,
+            }
+        });
+        folders.into_iter().map(|(_, rename)| rename).chain(files).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::re::{Preset, Rule, RuleMode};
+
+    fn file(name: &str, index: u32) -> TorrentFile {
+        TorrentFile { name: name.into(), index, size: 0 }
+    }
+
+    fn rename(kind: RenameKind, old: &str, new: &str) -> Rename {
+        Rename { kind, old: old.into(), new: new.into(), index: None }
+    }
+
+    fn file_rename(old: &str, new: &str, index: u32) -> Rename {
+        Rename { index: Some(index), ..rename(RenameKind::File, old, new) }
+    }
+
+    #[test]
+    fn test_2026_10_18_14_10_36() {
+        let torrent = TorrentInfo { hash: "abc".into(), name: "A.1".into() };
+        let files = [file("A.1/A.1.mkv", 0), file("A.1/A 1.mkv", 1), file("A.1/B.2.srt", 2), file("A.1/C 3.srt", 3), file("A.1/C.3.srt", 4)];
+        let renamer = Renamer::new(&[Rule::new(r"\.", " ")], RuleMode::Compound);
+
+        let plan = RenamePlan::new(&torrent, &files, &renamer, ConflictStrategy::Skip);
+        assert_eq!(plan.torrent_rename().unwrap().new, "A 1");
+        assert_eq!(plan.folder_renames().collect::<Vec<_>>(), [&rename(RenameKind::Folder, "A.1", "A 1")]);
+        assert_eq!(plan.file_renames().collect::<Vec<_>>(), [&file_rename("A 1/B.2.srt", "A 1/B 2.srt", 2)]);
+        assert_eq!(
+            plan.conflicts,
+            [
+                Conflict { target: "A 1/A 1.mkv".into(), sources: vec!["A.1/A.1.mkv".into(), "A.1/A 1.mkv".into()] },
+                Conflict { target: "A 1/C 3.srt".into(), sources: vec!["A.1/C 3.srt".into(), "A.1/C.3.srt".into()] },
+            ]
+        );
+
+        // 目标路径与不改名的文件相同
+        let plan = RenamePlan::new(&torrent, &[file("x.1.mkv", 0), file("x 1.mkv", 1)], &renamer, ConflictStrategy::Skip);
+        assert_eq!(plan.conflicts, [Conflict { target: "x 1.mkv".into(), sources: vec!["x.1.mkv".into(), "x 1.mkv".into()] }]);
+        assert_eq!(plan.file_renames().count(), 0);
+
+        // 每个目录只改名一次，由深到浅，目录之间的冲突同样检测
+        let files = [file("a.b/c.d/1.mkv", 0), file("a.b/c.d/2.mkv", 1), file("a.b/e/3.mkv", 2), file("f g/x", 3), file("f.g/y", 4)];
+        let plan = RenamePlan::new(&torrent, &files, &renamer, ConflictStrategy::Skip);
+        assert_eq!(
+            plan.folder_renames().collect::<Vec<_>>(),
+            [&rename(RenameKind::Folder, "a.b/c.d", "a.b/c d"), &rename(RenameKind::Folder, "a.b", "a b")]
+        );
+        assert_eq!(plan.file_renames().count(), 0);
+        assert_eq!(plan.conflicts, [Conflict { target: "f g".into(), sources: vec!["f g".into(), "f.g".into()] }]);
+    }
+
+    #[test]
+    fn test_2026_10_18_15_42_09() {
+        let torrent = TorrentInfo { hash: "abc".into(), name: "T".into() };
+        let files = [file("d/A.1.mkv", 0), file("d/A_1.mkv", 1), file("d/A 1 (2).mkv", 2), file("d/B.2.mkv", 3)];
+        let renamer = Renamer::new(&[Rule::new(r"[._]", " ")], RuleMode::Compound);
+        let targets = |strategy| {
+            let plan = RenamePlan::new(&torrent, &files, &renamer, strategy);
+            assert_eq!(plan.conflicts.len(), 1);
+            plan.file_renames().map(|r| r.new.clone()).collect::<Vec<_>>()
+        };
+
+        assert_eq!(targets(ConflictStrategy::Skip), ["d/A 1.mkv", "d/B 2.mkv"]);
+        // 序号跳过已存在的名称
+        assert_eq!(targets(ConflictStrategy::Suffix), ["d/A 1.mkv", "d/A 1 (3).mkv", "d/B 2.mkv"]);
+        assert_eq!(targets(ConflictStrategy::Keep), ["d/B 2.mkv"]);
+        assert!(targets(ConflictStrategy::Abort).is_empty());
+    }
+
+    #[test]
+    fn test_2026_10_18_21_52_14() {
+        let torrent = TorrentInfo { hash: "abc".into(), name: "Show.S01.1080p".into() };
+        let files = [
+            file("Show.S01.1080p/Show.S01E01.1080p.mkv", 0),
+            file("Show.S01.1080p/E02.mkv", 1),
+            file("Show.S01.1080p/Show.S01E02.1080p.mkv", 2),
+            file("Show.S01.1080p/info.nfo", 3),
+        ];
+        let renamer = Renamer::new(&[], RuleMode::Compound).with_preset(Preset::Tv);
+
+        // 顶层目录整体改名一次，其余文件移动到季目录下，不是视频的文件随顶层目录移动
+        let plan = RenamePlan::new(&torrent, &files, &renamer, ConflictStrategy::Suffix);
+        assert_eq!(
+            plan.renames,
+            [
+                rename(RenameKind::Torrent, "Show.S01.1080p", "Show"),
+                rename(RenameKind::Folder, "Show.S01.1080p", "Show"),
+                file_rename("Show/Show.S01E01.1080p.mkv", "Show/Season 01/Show - S01E01.mkv", 0),
+                file_rename("Show/E02.mkv", "Show/Season 01/Show - S01E02.mkv", 1),
+                file_rename("Show/Show.S01E02.1080p.mkv", "Show/Season 01/Show - S01E02 (2).mkv", 2),
+            ]
+        );
+        assert_eq!(plan.conflicts.len(), 1);
+
+        let plan = RenamePlan::new(&torrent, &files, &renamer, ConflictStrategy::Skip);
+        assert_eq!(plan.file_renames().count(), 2);
+
+        // 排在正片之前的花絮与样片不会占用正片的路径：附加内容目录与样片保持原位，其它较小的视频放到 Extras 下
+        let torrent = TorrentInfo { hash: "abc".into(), name: "Movie.2020.1080p.BluRay".into() };
+        let files = [
+            TorrentFile { size: 100, ..file("Movie.2020.1080p.BluRay/Bonus.Feature.mkv", 0) },
+            TorrentFile { size: 100, ..file("Movie.2020.1080p.BluRay/Extras/Behind.the.Scenes.mkv", 1) },
+            TorrentFile { size: 5000, ..file("Movie.2020.1080p.BluRay/Movie.2020.1080p.BluRay.mkv", 2) },
+            TorrentFile { size: 10, ..file("Movie.2020.1080p.BluRay/sample.mkv", 3) },
+        ];
+        for preset in [Preset::Movie, Preset::Auto] {
+            let renamer = Renamer::new(&[], RuleMode::Compound).with_preset(preset);
+            let plan = RenamePlan::new(&torrent, &files, &renamer, ConflictStrategy::Skip);
+            assert!(plan.conflicts.is_empty());
+            assert_eq!(plan.folder_renames().collect::<Vec<_>>(), [&rename(RenameKind::Folder, "Movie.2020.1080p.BluRay", "Movie (2020)")]);
+            assert_eq!(
+                plan.file_renames().collect::<Vec<_>>(),
+                [
+                    &file_rename("Movie (2020)/Bonus.Feature.mkv", "Movie (2020)/Extras/Bonus.Feature.mkv", 0),
+                    &file_rename("Movie (2020)/Movie.2020.1080p.BluRay.mkv", "Movie (2020)/Movie (2020).mkv", 2),
+                ]
+            );
+        }
+    }
+}
@@ -1,169 +1,201 @@
-use crate::{log, re};
+use crate::client::{self, TorrentClient, TorrentFile, TorrentFilter, TorrentInfo};
+use crate::error::Error;
+use crate::{log, retry};
+
+use crate::plan::Rename;
+use reqwest::header::{HeaderMap, HeaderValue, ORIGIN, REFERER};
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use std::collections::HashMap;
+
+/// qBittorrent WebUI 客户端，会话保存在 `client` 的 Cookie 中
+pub struct QBittorrent {
+    client: Client,
+    webui_url: String,
+    credentials: Option<(String, String)>,
+}
 
-use crate::re::CompoundReplacer;
-use reqwest::Client;
-use serde::{Deserialize, Serialize};
-use tokio::task::JoinSet;
+/// `/api/v2/sync/maindata` 的响应，`full_update` 为 false 时只包含自上次 `rid` 以来变化的字段
+#[derive(Deserialize, Debug, Default)]
+pub struct MainData {
+    pub rid: u64,
+    #[serde(default)]
+    pub full_update: bool,
+    #[serde(default)]
+    pub torrents: HashMap<String, TorrentPatch>,
+    #[serde(default)]
+    pub torrents_removed: Vec<String>,
+}
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct TorrentInfo {
-    pub hash: String,
-    pub name: String,
+/// 种子变化的字段，未变化的字段不会出现
+#[derive(Deserialize, Debug, Default)]
+pub struct TorrentPatch {
+    pub name: Option<String>,
+    pub state: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-pub struct TorrentFile {
-    pub name: String,
-    pub index: u32,
+/// qBittorrent 5 默认开启 CSRF 防护，远程访问时请求需带上与 WebUI 地址一致的 `Referer` 与 `Origin`
+pub fn csrf_headers(webui_url: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    if let Ok(url) = Url::parse(webui_url) {
+        if let Ok(referer) = HeaderValue::from_str(url.as_str()) {
+            headers.insert(REFERER, referer);
+        }
+        if let Ok(origin) = HeaderValue::from_str(&url.origin().ascii_serialization()) {
+            headers.insert(ORIGIN, origin);
+        }
+    }
+    headers
 }
 
-pub async fn authenticate(
-    client: &Client,
-    webui_url: &str,
-    username: &str,
-    password: &str,
-) -> Result<(), String> {
-    let auth_url = format!("{}/api/v2/auth/login", webui_url);
-    let auth_params = [("username", username), ("password", password)];
-    client
-        .post(&auth_url)
-        .form(&auth_params)
-        .send()
-        .await
-        .and_then(|resp| resp.error_for_status())
-        .map_err(|e| format!("Failed to authenticate: {}", e))?;
+impl QBittorrent {
+    pub fn new(client: Client, webui_url: String, credentials: Option<(String, String)>) -> Self {
+        Self { client, webui_url, credentials }
+    }
 
-    log!("Authentication successful");
-    Ok(())
+    /// 如果提供了用户名和密码则登录 WebUI，会话失效后可再次调用
+    pub async fn login(&self) -> Result<(), Error> {
+        match &self.credentials {
+            Some((username, password)) => authenticate(&self.client, &self.webui_url, username, password).await,
+            None => {
+                log!("Skipping authentication as username and/or password were not provided.");
+                Ok(())
+            }
+        }
+    }
+
+    /// 获取自 `rid` 以来的增量数据，会话失效时返回 [`Error::Auth`]
+    pub async fn sync_maindata(&self, rid: u64) -> Result<MainData, Error> {
+        // https://github.com/qbittorrent/qBittorrent/wiki/WebUI-API-(qBittorrent-4.1)#get-main-data
+        retry::send(self.client.get(format!("{}/api/v2/sync/maindata", self.webui_url)).query(&[("rid", rid)]))
+            .await
+            .map_err(|e| Error::http("Failed to sync main data", e))?
+            .json()
+            .await
+            .map_err(|e| Error::http("Failed to parse main data", e))
+    }
 }
 
-pub async fn get_torrent_info(
-    client: &Client,
-    webui_url: &str,
-    torrent_hash: &str,
-) -> Result<TorrentInfo, String> {
-    // https://github.com/qbittorrent/qBittorrent/wiki/WebUI-API-(qBittorrent-4.1)#get-torrent-list
-    let info_url = format!("{}/api/v2/torrents/info?hashes={}", webui_url, torrent_hash);
-    let info_response = client
-        .get(&info_url)
-        .send()
-        .await
-        .and_then(|resp| resp.error_for_status())
-        .map_err(|e| format!("Failed to fetch torrent info: {}", e))?;
-
-    let torrent_info: Vec<TorrentInfo> = info_response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse torrent info: {}", e))?;
-
-    if torrent_info.is_empty() {
-        return Err(format!("No torrent found with hash: {}", torrent_hash));
+impl TorrentClient for QBittorrent {
+    async fn list_torrents(&self, filter: &TorrentFilter) -> Result<Vec<TorrentInfo>, Error> {
+        // https://github.com/qbittorrent/qBittorrent/wiki/WebUI-API-(qBittorrent-4.1)#get-torrent-list
+        let mut query = vec![];
+        if !filter.hashes.is_empty() { query.push(("hashes", filter.hashes.join("|"))); }
+        if let Some(category) = &filter.category { query.push(("category", category.clone())); }
+        if let Some(tag) = &filter.tag { query.push(("tag", tag.clone())); }
+        if let Some(state) = &filter.state { query.push(("filter", state.clone())); }
+
+        let info_response = retry::send(self.client.get(format!("{}/api/v2/torrents/info", self.webui_url)).query(&query))
+            .await
+            .map_err(|e| Error::http("Failed to fetch torrent info", e))?;
+
+        info_response
+            .json()
+            .await
+            .map_err(|e| Error::http("Failed to parse torrent info", e))
     }
 
-    log!("Fetched torrent info for hash: {}", torrent_hash);
-    Ok(torrent_info.into_iter().next().unwrap())
-}
+    async fn get_files(&self, hash: &str) -> Result<Vec<TorrentFile>, Error> {
+        // https://github.com/qbittorrent/qBittorrent/wiki/WebUI-API-(qBittorrent-4.1)#get-torrent-contents
+        let files_url = format!("{}/api/v2/torrents/files?hash={}", self.webui_url, hash);
+        let files_response = retry::send(self.client.get(&files_url))
+            .await
+            .map_err(|e| Error::http("Failed to fetch torrent files", e))?;
 
-pub async fn get_torrent_files(
-    client: &Client,
-    webui_url: &str,
-    torrent_hash: &str,
-) -> Result<Vec<TorrentFile>, String> {
-    // https://github.com/qbittorrent/qBittorrent/wiki/WebUI-API-(qBittorrent-4.1)#get-torrent-contents
-    let files_url = format!("{}/api/v2/torrents/files?hash={}", webui_url, torrent_hash);
-    let files_response = client
-        .get(&files_url)
-        .send()
-        .await
-        .and_then(|resp| resp.error_for_status())
-        .map_err(|e| format!("Failed to fetch torrent files: {}", e))?;
-
-    let torrent_files: Vec<TorrentFile> = files_response
-        .json()
-        .await
-        .map_err(|e| format!("Failed to parse torrent files: {}", e))?;
-
-    Ok(torrent_files)
-}
+        files_response
+            .json()
+            .await
+            .map_err(|e| Error::http("Failed to parse torrent files", e))
+    }
 
-pub async fn rename_torrent(
-    client: &Client,
-    webui_url: &str,
-    torrent_hash: &str,
-    compound_replacer: &CompoundReplacer,
-) -> Result<(), String> {
-    let torrent = get_torrent_info(client, webui_url, torrent_hash).await?;
-    let new_name = compound_replacer.replace(&torrent.name);
-
-    if torrent.name != new_name {
-        client
-            .post(format!("{}/api/v2/torrents/rename", webui_url))
-            .form(&[("hash", &torrent.hash), ("name", &new_name)])
-            .send()
+    async fn rename_torrent(&self, hash: &str, name: &str) -> Result<(), Error> {
+        let request = self
+            .client
+            .post(format!("{}/api/v2/torrents/rename", self.webui_url))
+            .form(&[("hash", hash), ("name", name)]);
+        // 重试前确认种子名称是否已经改好
+        retry::send_unless_applied(request, || client::torrent_renamed(self, hash, name))
             .await
-            .and_then(|resp| resp.error_for_status())
-            .map_err(|e| format!("Failed to rename torrent: {}", e))?;
+            .map_err(|e| Error::http("Failed to rename torrent", e))?;
+        Ok(())
+    }
 
-        log!("Successfully renamed torrent to: {}", new_name);
+    async fn rename_path(&self, hash: &str, rename: &Rename, is_dir: bool) -> Result<(), Error> {
+        let (endpoint, kind) = if is_dir { ("renameFolder", "folder") } else { ("renameFile", "file") };
+        let request = self
+            .client
+            .post(format!("{}/api/v2/torrents/{}", self.webui_url, endpoint))
+            .form(&[("hash", hash), ("oldPath", &rename.old), ("newPath", &rename.new)]);
+        // 重试前确认目录或文件是否已经改名
+        retry::send_unless_applied(request, || client::path_renamed(self, hash, rename, is_dir))
+            .await
+            .map_err(|e| Error::http(&format!("Failed to rename {}: {} -> {}", kind, rename.old, rename.new), e))?;
+        Ok(())
     }
 
-    Ok(())
+    async fn add_tags(&self, hash: &str, tags: &[String]) -> Result<(), Error> {
+        // https://github.com/qbittorrent/qBittorrent/wiki/WebUI-API-(qBittorrent-4.1)#add-torrent-tags
+        let request = self
+            .client
+            .post(format!("{}/api/v2/torrents/addTags", self.webui_url))
+            .form(&[("hashes", hash), ("tags", &tags.join(","))]);
+        retry::send(request).await.map_err(|e| Error::http("Failed to add tags", e))?;
+        Ok(())
+    }
 }
 
-pub async fn rename_files(
+/// 登录 WebUI。qBittorrent 对错误的用户名或密码同样返回 200，只能通过响应内容 `Fails.` 判断，
+/// 失败次数过多时会封禁 IP 并返回 403
+async fn authenticate(
     client: &Client,
     webui_url: &str,
-    torrent_hash: &str,
-    compound_replacer: &CompoundReplacer,
-) -> Result<(), String> {
-    let torrent_files: Vec<TorrentFile> = get_torrent_files(client, webui_url, torrent_hash).await?;
-    let rename_url = format!("{webui_url}/api/v2/torrents/renameFile");
-    let mut tasks = JoinSet::new();
-
-    // 并行处理每个文件重命名
-    for file in torrent_files {
-        let new_name = apply_rename_rules_to_file(&file.name, compound_replacer);
-        if file.name == new_name {
-            continue;
-        }
-
-        let (client, url, hash) = (client.clone(), rename_url.clone(), torrent_hash.to_owned());
-
-        tasks.spawn(async move {
-            // 发送重命名请求并处理响应
-            let result = client
-                .post(&url)
-                .form(&[("hash", &hash), ("oldPath", &file.name), ("newPath", &new_name)])
-                .send()
-                .await
-                .and_then(|resp| resp.error_for_status());
-            // 返回处理结果与元数据
-            (file.name, new_name, result)
-        });
-    }
-
-    // 统一处理所有任务结果
-    while let Some(res) = tasks.join_next().await {
-        match res {
-            Ok((old, new, Ok(_))) => log!("Success: {} -> {}",old,new),
-            Ok((old, new, Err(e))) => log!("Failed: {} -> {} | {}",old,new,e),
-            Err(e) => log!("Task execution failed: {}",e),
-        }
+    username: &str,
+    password: &str,
+) -> Result<(), Error> {
+    // https://github.com/qbittorrent/qBittorrent/wiki/WebUI-API-(qBittorrent-4.1)#login
+    let auth_url = format!("{}/api/v2/auth/login", webui_url);
+    let auth_params = [("username", username), ("password", password)];
+    let response = match retry::send(client.post(&auth_url).form(&auth_params)).await {
+        Ok(response) => response,
+        Err(e) if e.status() == Some(StatusCode::FORBIDDEN) => return login_result(StatusCode::FORBIDDEN, ""),
+        Err(e) => return Err(Error::http("Failed to authenticate", e)),
+    };
+    let status = response.status();
+    // qBittorrent 5 的会话 Cookie 名称可能带有端口后缀，如 QBT_SID_8080
+    let has_sid = response.cookies().any(|c| c.name().ends_with("SID"));
+    let body = response.text().await.map_err(|e| Error::http("Failed to authenticate", e))?;
+
+    login_result(status, &body)?;
+    if !has_sid {
+        log!("Warning: login succeeded but no session cookie was set");
     }
+    log!("Authentication successful");
     Ok(())
 }
 
-/// 将文件名应用重命名规则，不改变文件扩展名
-fn apply_rename_rules_to_file(name: &str, compound_replacer: &CompoundReplacer) -> String {
-    let (stem, ext) = re::split_filename(name);
-
-    // 仅对主名部分应用替换规则
-    let stem = compound_replacer.replace(stem.as_str());
+/// 按登录响应的状态码与正文判断是否登录成功：403 表示 IP 因多次登录失败被封禁，
+/// 否则正文为 `Ok.` 表示成功、`Fails.` 表示用户名或密码错误
+fn login_result(status: StatusCode, body: &str) -> Result<(), Error> {
+    if status == StatusCode::FORBIDDEN {
+        return Err(Error::Auth("IP is banned by qBittorrent for too many failed login attempts".to_string()));
+    }
+    match body.trim() {
+        "Ok." => Ok(()),
+        "Fails." => Err(Error::Auth("Invalid username or password".to_string())),
+        other => Err(Error::Auth(format!("Unexpected login response: {:?}", other))),
+    }
+}
 
-    // 重新组合主名和扩展名
-    if ext.is_empty() {
-        stem.to_string()
-    } else {
-        format!("{}.{}", stem, ext)
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_19_11_42_05() {
+        assert!(login_result(StatusCode::OK, "Ok.").is_ok());
+        assert!(login_result(StatusCode::OK, "Ok.\n").is_ok());
+        assert!(matches!(login_result(StatusCode::OK, "Fails."), Err(Error::Auth(m)) if m.contains("username or password")));
+        assert!(matches!(login_result(StatusCode::FORBIDDEN, ""), Err(Error::Auth(m)) if m.contains("banned")));
+        assert!(matches!(login_result(StatusCode::OK, "<html>"), Err(Error::Auth(m)) if m.contains("Unexpected")));
     }
 }
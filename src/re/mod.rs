@@ -1,8 +1,16 @@
 use regex_automata::{dfa::Automaton, Anchored, Input};
 
 mod split;
+mod preset;
+mod release;
+mod rename;
 mod replace;
-pub use crate::re::replace::CompoundReplacer as CompoundReplacer;
+mod rule;
+pub use crate::re::preset::{is_extra, Layout, Preset};
+pub use crate::re::release::Template;
+pub use crate::re::rename::{PartTrace, Renamer};
+pub use crate::re::replace::{RuleMatch, RuleMode, RuleSet};
+pub use crate::re::rule::{Rule, RuleKind, Scope, DEFAULT_SCOPE};
 
 /// 将文件名拆分为主名和扩展名 FILE_EXTENSION_SPLIT  
 /// 使用 regex_cli 对正则表达式进行预编译，运行时通过读取字节反序列化，减少90%的运行时开销  
@@ -0,0 +1,127 @@
+use crate::re::release::Release;
+use clap::ValueEnum;
+
+/// 媒体服务器（Plex、Jellyfin）的命名预设，决定文件在种子内的目录结构
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Preset {
+    /// `Movie Title (Year)/Movie Title (Year).mkv`
+    Movie,
+    /// `Show/Season 01/Show - S01E02.mkv`
+    Tv,
+    /// 识别出集数的文件按剧集，季包以外的其余文件按电影
+    Auto,
+}
+
+const VIDEO_EXTENSIONS: &[&str] = &["mkv", "mp4", "m4v", "avi", "m2ts", "ts", "wmv", "mov", "webm", "flv", "rmvb"];
+const SUBTITLE_EXTENSIONS: &[&str] = &["srt", "ass", "ssa", "sub", "idx", "sup", "vtt"];
+
+/// 字幕的语言标记，如 `zh`、`chs`、`zh-CN`、`zh-Hans`
+fn is_language(tag: &str) -> bool {
+    let (language, region) = tag.split_once('-').unwrap_or((tag, "AA"));
+    (2..=3).contains(&language.len())
+        && language.chars().all(|c| c.is_ascii_alphabetic())
+        && (2..=4).contains(&region.len())
+        && region.chars().all(|c| c.is_ascii_alphanumeric())
+}
+
+/// 样片、花絮等附加内容的文件名或目录名，这些文件保持原位
+pub fn is_extra(name: &str) -> bool {
+    let name = name.replace(['.', '_', '-'], " ").to_lowercase();
+    ["sample", "samples", "extra", "extras", "featurettes", "behind the scenes", "deleted scenes", "trailer", "trailers", "花絮", "特典"]
+        .contains(&name.trim())
+}
+
+/// 文件按预设安排后的位置
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Layout {
+    pub path: String,
+    /// 电影的视频不是正片（样片、花絮等）时改用的路径，位于电影目录的 `Extras/` 下并保留原文件名
+    pub extra: Option<String>,
+}
+
+impl Preset {
+    /// 文件在媒体服务器目录结构中相对于种子根目录的新路径。`stem` 与 `context`（顶层目录或种子名称）
+    /// 都已经过规则预清理，文件名中缺少的标题、年份与季从 `context` 中补充。
+    /// 不是视频或字幕的文件、以及按剧集整理却没有集数的文件返回 `None`，保持原路径；`file_name` 为原文件名
+    pub fn layout(self, stem: &str, extension: &str, context: &str, file_name: &str) -> Option<Layout> {
+        let ext = extension.to_lowercase();
+        let is_subtitle = SUBTITLE_EXTENSIONS.contains(&ext.as_str());
+        if !is_subtitle && !VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+            return None;
+        }
+        // 字幕的语言标记保留在扩展名之前；主名本身就是语言标记（如 eng.srt）时标题取自目录
+        let (stem, language) = match stem.rsplit_once('.') {
+            _ if is_subtitle && is_language(stem) => ("", format!(".{}", stem)),
+            Some((rest, tag)) if is_subtitle && is_language(tag) => (rest, format!(".{}", tag)),
+            _ => (stem, String::new()),
+        };
+
+        let mut release = Release::parse(stem);
+        let context = Release::parse(context);
+        // 只有集数的文件名（如 S01E02.mkv）或没有任何元数据的文件名，标题以目录为准
+        if release.title.is_empty() || !release.has_metadata() {
+            release.title = context.title;
+        }
+        if release.title.is_empty() {
+            return None;
+        }
+        release.year = release.year.or(context.year);
+        release.season = release.season.or(context.season);
+
+        let title = &release.title;
+        let mut extra = None;
+        let path = match (self, release.episode) {
+            (Preset::Tv | Preset::Auto, Some(episode)) => {
+                let season = release.season.unwrap_or(1);
+                format!("{0}/Season {1:02}/{0} - S{1:02}E{2:02}", title, season, episode)
+            }
+            (Preset::Tv, None) => return None,
+            // 季包中没有集数的文件（样片、花絮等）也不按电影整理
+            (Preset::Auto, None) if release.season.is_some() => return None,
+            (Preset::Movie | Preset::Auto, _) => {
+                let name = match release.year {
+                    Some(year) => format!("{} ({})", title, year),
+                    None => title.clone(),
+                };
+                if !is_subtitle {
+                    extra = Some(format!("{}/Extras/{}", name, file_name));
+                }
+                format!("{0}/{0}", name)
+            }
+        };
+        Some(Layout { path: format!("{}{}.{}", path, language, extension), extra })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_21_37_50() {
+        let layout = |preset: Preset, stem: &str, ext: &str, context: &str| {
+            preset.layout(stem, ext, context, &format!("{}.{}", stem, ext)).map(|l| l.path)
+        };
+        let movie = "The.Matrix.1999.1080p.BluRay.x264-GRP";
+        assert_eq!(
+            Preset::Movie.layout(movie, "mkv", "x", "a.mkv"),
+            Some(Layout {
+                path: "The Matrix (1999)/The Matrix (1999).mkv".into(),
+                extra: Some("The Matrix (1999)/Extras/a.mkv".into()),
+            })
+        );
+        assert_eq!(layout(Preset::Auto, "movie", "mkv", movie).as_deref(), Some("The Matrix (1999)/The Matrix (1999).mkv"));
+        assert_eq!(layout(Preset::Auto, &format!("{}.chs", movie), "ass", "x").as_deref(), Some("The Matrix (1999)/The Matrix (1999).chs.ass"));
+        // 主名本身就是语言标记
+        assert_eq!(layout(Preset::Movie, "eng", "srt", movie).as_deref(), Some("The Matrix (1999)/The Matrix (1999).eng.srt"));
+        assert_eq!(layout(Preset::Movie, "zh-CN", "srt", movie).as_deref(), Some("The Matrix (1999)/The Matrix (1999).zh-CN.srt"));
+        assert_eq!(layout(Preset::Auto, movie, "nfo", "x"), None);
+        assert!(is_extra("Behind.the.Scenes") && is_extra("Sample") && !is_extra("Samples of Life"));
+
+        let show = "The.Expanse.S02.1080p.WEB-DL";
+        assert_eq!(layout(Preset::Tv, "S02E05", "mkv", show).as_deref(), Some("The Expanse/Season 02/The Expanse - S02E05.mkv"));
+        assert_eq!(layout(Preset::Auto, "Show.E07.720p", "mp4", "x").as_deref(), Some("Show/Season 01/Show - S01E07.mp4"));
+        assert_eq!(layout(Preset::Tv, "sample", "mkv", show), None);
+        assert_eq!(layout(Preset::Auto, "sample", "mkv", show), None);
+    }
+}
@@ -0,0 +1,352 @@
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// 从发布名称中解析出的元数据，未识别的字段为空
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Release {
+    pub title: String,
+    pub year: Option<u16>,
+    pub season: Option<u32>,
+    pub episode: Option<u32>,
+    pub resolution: Option<String>,
+    pub source: Vec<String>,
+    pub codec: Option<String>,
+    pub audio: Vec<String>,
+    pub hdr: Vec<String>,
+    pub group: Option<String>,
+}
+
+static LEADING_TAGS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?:\s*[\[【]([^\]】]*)[\]】])+\s*").unwrap());
+static YEAR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\b(19\d{2}|20\d{2})\b").unwrap());
+static RESOLUTION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(?:(\d{3,4})[pi]|\d{3,4}x(\d{3,4})|(4K))\b").unwrap());
+static SOURCE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(UHD|Blu-?Ray|BDRip|BRRip|WEB-?DL|WEB-?Rip|WEB|HDTV|DVDRip|DVD|Remux|HDRip)\b").unwrap()
+});
+static CODEC: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\b(x26[45]|H\.?26[45]|HEVC|AVC|AV1|VP9|XviD)\b").unwrap());
+// 末尾的 `(?:$|[^\w])` 代替 `\b`，使 `DD+`、`HDR10+` 这类以符号结尾的标识也能匹配
+static AUDIO: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(DTS-HD(?:[ .]?MA)?|DTS-?X|DTS|TrueHD|Atmos|DDP|DD\+|E-?AC-?3|AC-?3|DD|AAC|FLAC|Opus|L?PCM)(?:[ .]?(\d\.\d))?(?:$|[^\w])").unwrap()
+});
+static HDR: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)\b(HDR10\+|HDR10|HDR|DoVi|Dolby[ .]?Vision|HLG|DV)(?:$|[^\w])|(杜比视界)").unwrap()
+});
+static GROUP: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"-([A-Za-z0-9]+)\s*$").unwrap());
+/// 季与集的写法，按顺序尝试，前面的写法优先
+static EPISODE_PATTERNS: LazyLock<Vec<(Regex, EpisodeParts)>> = LazyLock::new(|| {
+    [
+        (r"(?i)\bS(\d{1,2})[ ._-]?E(\d{1,4})\b", EpisodeParts::Both),
+        (r"\b(\d{1,2})x(\d{2,3})\b", EpisodeParts::Both),
+        (r"第(\d{1,3})季", EpisodeParts::Season),
+        (r"第(\d{1,4})[集话話]", EpisodeParts::Episode),
+        (r"(?i)\b(?:S|Season[ ._]?)(\d{1,2})\b", EpisodeParts::Season),
+        (r"(?i)\bEP?(\d{1,4})\b", EpisodeParts::Episode),
+    ]
+    .into_iter()
+    .map(|(pattern, parts)| (Regex::new(pattern).unwrap(), parts))
+    .collect()
+});
+
+#[derive(Clone, Copy)]
+enum EpisodeParts {
+    Both,
+    Season,
+    Episode,
+}
+
+/// 统一同一标识的不同写法，`None` 表示保持原样
+fn canonical(token: &str) -> Option<&'static str> {
+    let key: String = token.to_lowercase().chars().filter(|c| !matches!(c, '-' | '.' | ' ')).collect();
+    Some(match key.as_str() {
+        "uhd" => "UHD",
+        "bluray" => "BluRay",
+        "bdrip" => "BDRip",
+        "brrip" => "BRRip",
+        "webdl" => "WEB-DL",
+        "webrip" => "WEBRip",
+        "web" => "WEB",
+        "hdtv" => "HDTV",
+        "dvdrip" => "DVDRip",
+        "dvd" => "DVD",
+        "remux" => "Remux",
+        "hdrip" => "HDRip",
+        "x264" => "x264",
+        "x265" => "x265",
+        "h264" => "H.264",
+        "h265" => "H.265",
+        "hevc" => "HEVC",
+        "avc" => "AVC",
+        "av1" => "AV1",
+        "vp9" => "VP9",
+        "xvid" => "XviD",
+        "dtshdma" => "DTS-HD MA",
+        "dtshd" => "DTS-HD",
+        "dtsx" => "DTS:X",
+        "dts" => "DTS",
+        "truehd" => "TrueHD",
+        "atmos" => "Atmos",
+        "ddp" | "dd+" | "eac3" => "DDP",
+        "dd" | "ac3" => "DD",
+        "aac" => "AAC",
+        "flac" => "FLAC",
+        "opus" => "Opus",
+        "lpcm" => "LPCM",
+        "pcm" => "PCM",
+        "hdr10+" => "HDR10+",
+        "hdr10" => "HDR10",
+        "hdr" => "HDR",
+        "dv" | "dovi" | "dolbyvision" | "杜比视界" => "DV",
+        "hlg" => "HLG",
+        _ => return None,
+    })
+}
+
+fn push_unique(list: &mut Vec<String>, token: &str) {
+    let token = canonical(token).map_or_else(|| token.to_string(), str::to_string);
+    if !list.contains(&token) {
+        list.push(token);
+    }
+}
+
+impl Release {
+    /// 解析发布名称。标题取开头的标签之后、第一个识别出的元数据之前的部分，其中的 `.` 与 `_` 视为空格
+    pub fn parse(name: &str) -> Self {
+        let mut release = Release::default();
+        let leading = LEADING_TAGS.captures(name);
+        let title_start = leading.as_ref().map_or(0, |c| c.get(0).unwrap().end());
+        // 元数据中最靠前的位置，标题在此结束
+        let mut first = name.len();
+        let mut mark = |start: usize| {
+            if start >= title_start {
+                first = first.min(start);
+            }
+        };
+
+        for (regex, parts) in EPISODE_PATTERNS.iter() {
+            for caps in regex.captures_iter(&name[title_start..]) {
+                let number = |i: usize| caps.get(i).and_then(|m| m.as_str().parse().ok());
+                let (season, episode) = match parts {
+                    EpisodeParts::Both => (number(1), number(2)),
+                    EpisodeParts::Season => (number(1), None),
+                    EpisodeParts::Episode => (None, number(1)),
+                };
+                if season.is_some() && release.season.is_some() || episode.is_some() && release.episode.is_some() {
+                    continue;
+                }
+                release.season = release.season.or(season);
+                release.episode = release.episode.or(episode);
+                mark(title_start + caps.get(0).unwrap().start());
+            }
+        }
+        if let Some(caps) = RESOLUTION.captures(name) {
+            let height = caps.get(1).or(caps.get(2)).map_or("2160", |m| m.as_str());
+            release.resolution = Some(format!("{}p", height));
+            mark(caps.get(0).unwrap().start());
+        }
+        for m in SOURCE.find_iter(name) {
+            push_unique(&mut release.source, m.as_str());
+            mark(m.start());
+        }
+        if let Some(m) = CODEC.find(name) {
+            release.codec = Some(canonical(m.as_str()).unwrap().to_string());
+            mark(m.start());
+        }
+        for caps in AUDIO.captures_iter(name) {
+            let codec = canonical(&caps[1]).unwrap();
+            let audio = match caps.get(2) {
+                Some(channels) => format!("{} {}", codec, channels.as_str()),
+                None => codec.to_string(),
+            };
+            push_unique(&mut release.audio, &audio);
+            mark(caps.get(0).unwrap().start());
+        }
+        for caps in HDR.captures_iter(name) {
+            push_unique(&mut release.hdr, caps.get(1).or(caps.get(2)).unwrap().as_str());
+            mark(caps.get(0).unwrap().start());
+        }
+
+        // 年份可能就是标题（如 1917），取其它元数据之前的最后一个年份，没有时取之后的第一个
+        let years: Vec<_> = YEAR.find_iter(name).filter(|m| m.start() > title_start).collect();
+        let year = years.iter().rfind(|m| m.start() <= first).or(years.first());
+        if let Some(m) = year {
+            release.year = m.as_str().parse().ok();
+            first = first.min(m.start());
+        }
+
+        // 末尾的 -GROUP 只在元数据之后才算发布组，否则可能是标题的一部分；动画常把发布组放在开头的方括号中
+        release.group = GROUP
+            .captures(name)
+            .filter(|caps| caps.get(0).unwrap().start() > first && first < name.len())
+            .map(|caps| caps[1].to_string())
+            .or_else(|| leading.and_then(|c| c.get(1)).map(|m| m.as_str().trim().to_string()).filter(|g| !g.is_empty()));
+
+        let title = name[title_start..first.max(title_start)].replace(['.', '_'], " ");
+        let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+        release.title = title
+            .trim_end_matches(|c: char| c.is_whitespace() || "-([【（".contains(c))
+            .to_string();
+        release
+    }
+
+    /// 是否识别出了标题以外的元数据
+    pub fn has_metadata(&self) -> bool {
+        *self != Release { title: self.title.clone(), group: self.group.clone(), ..Default::default() }
+    }
+}
+
+/// 模板中的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Field {
+    Title,
+    Year,
+    Season,
+    Episode,
+    /// `S01E02`，只有季或只有集时为 `S01` 或 `E02`
+    SeasonEpisode,
+    Resolution,
+    Source,
+    Codec,
+    Audio,
+    Hdr,
+    Group,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    /// 字段与数字字段补零后的宽度
+    Field(Field, usize),
+}
+
+/// 由发布元数据生成名称的模板，如 `{title} ({year}) [{resolution} {source}]`。
+/// `{season}`、`{episode}` 默认补零到两位，可写作 `{episode:3}` 指定宽度
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+static EMPTY_BRACKETS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\(\s*\)|\[\s*\]|【\s*】|（\s*）").unwrap());
+static BRACKET_SPACES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"([(\[【（])\s+|\s+([)\]】）])").unwrap());
+
+impl Template {
+    pub fn new(template: &str) -> Result<Self, String> {
+        let mut segments = vec![];
+        let mut rest = template;
+        while let Some(open) = rest.find('{') {
+            if open > 0 {
+                segments.push(Segment::Literal(rest[..open].to_string()));
+            }
+            let close = rest[open..].find('}').ok_or_else(|| format!("Unclosed '{{' in template {:?}", template))? + open;
+            let placeholder = &rest[open + 1..close];
+            let (name, width) = match placeholder.split_once(':') {
+                Some((name, width)) => {
+                    let width = width.parse().map_err(|_| format!("Invalid width in {{{}}}", placeholder))?;
+                    (name, width)
+                }
+                None => (placeholder, 2),
+            };
+            let field = match name {
+                "title" => Field::Title,
+                "year" => Field::Year,
+                "season" => Field::Season,
+                "episode" => Field::Episode,
+                "se" => Field::SeasonEpisode,
+                "resolution" => Field::Resolution,
+                "source" => Field::Source,
+                "codec" => Field::Codec,
+                "audio" => Field::Audio,
+                "hdr" => Field::Hdr,
+                "group" => Field::Group,
+                _ => return Err(format!(
+                    "Unknown field {{{}}}, expected one of title, year, season, episode, se, resolution, source, codec, audio, hdr, group",
+                    name
+                )),
+            };
+            segments.push(Segment::Field(field, width));
+            rest = &rest[close + 1..];
+        }
+        if !rest.is_empty() {
+            segments.push(Segment::Literal(rest.to_string()));
+        }
+        Ok(Self { segments })
+    }
+
+    /// 按模板生成名称，缺失字段留空，随后去掉空括号与多余的空格
+    pub fn render(&self, release: &Release) -> String {
+        let pad = |n: Option<u32>, width: usize| n.map(|n| format!("{:0width$}", n, width = width)).unwrap_or_default();
+        let mut out = String::new();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(s) => out.push_str(s),
+                Segment::Field(field, width) => out.push_str(&match field {
+                    Field::Title => release.title.clone(),
+                    Field::Year => release.year.map(|y| y.to_string()).unwrap_or_default(),
+                    Field::Season => pad(release.season, *width),
+                    Field::Episode => pad(release.episode, *width),
+                    Field::SeasonEpisode => {
+                        let season = release.season.map(|_| format!("S{}", pad(release.season, *width)));
+                        let episode = release.episode.map(|_| format!("E{}", pad(release.episode, *width)));
+                        season.unwrap_or_default() + &episode.unwrap_or_default()
+                    }
+                    Field::Resolution => release.resolution.clone().unwrap_or_default(),
+                    Field::Source => release.source.join(" "),
+                    Field::Codec => release.codec.clone().unwrap_or_default(),
+                    Field::Audio => release.audio.join(" "),
+                    Field::Hdr => release.hdr.join(" "),
+                    Field::Group => release.group.clone().unwrap_or_default(),
+                }),
+            }
+        }
+        let out = BRACKET_SPACES.replace_all(&out, "$1$2");
+        let out = EMPTY_BRACKETS.replace_all(&out, "");
+        let out = out.split_whitespace().collect::<Vec<_>>().join(" ");
+        out.trim_matches(|c: char| c.is_whitespace() || c == '-').to_string()
+    }
+
+    /// 解析名称并按模板重新生成；没有识别出任何元数据时返回 `None`，保持原名
+    pub fn apply(&self, name: &str) -> Option<String> {
+        let release = Release::parse(name);
+        if release.title.is_empty() || !release.has_metadata() {
+            return None;
+        }
+        Some(self.render(&release))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_21_03_44() {
+        let release = Release::parse("小丑2：双重妄想[HDR+杜比视界双版本][中文字幕].2024.2160p.UHD.BluRay.Remux.DV.HEVC.TrueHD7.1-ParkHD");
+        assert_eq!(
+            release,
+            Release {
+                title: "小丑2：双重妄想".into(),
+                year: Some(2024),
+                resolution: Some("2160p".into()),
+                source: vec!["UHD".into(), "BluRay".into(), "Remux".into()],
+                codec: Some("HEVC".into()),
+                audio: vec!["TrueHD 7.1".into()],
+                hdr: vec!["HDR".into(), "DV".into()],
+                group: Some("ParkHD".into()),
+                ..Default::default()
+            }
+        );
+        let template = Template::new("{title} ({year}) [{resolution} {source}]").unwrap();
+        assert_eq!(template.render(&release), "小丑2：双重妄想 (2024) [2160p UHD BluRay Remux]");
+
+        let release = Release::parse("[SubsPlease] Some Show - S02E05 (1080p) [ABCD1234]");
+        assert_eq!((release.title.as_str(), release.season, release.episode), ("Some Show", Some(2), Some(5)));
+        assert_eq!(release.group.as_deref(), Some("SubsPlease"));
+        assert_eq!(Release::parse("1917.2019.1080p.WEB-DL.DDP5.1.H.264-GRP").title, "1917");
+        assert_eq!(Release::parse("某剧.第2季.第10集.1080p").episode, Some(10));
+
+        // 缺失的字段连同空括号一起去掉
+        let template = Template::new("{title} - {se:2} [{resolution} {codec}]").unwrap();
+        assert_eq!(template.apply("Show.S01E02.HDTV").as_deref(), Some("Show - S01E02"));
+        assert_eq!(template.apply("readme"), None);
+        assert!(Template::new("{title} {bogus}").is_err());
+    }
+}
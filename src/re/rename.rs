@@ -0,0 +1,176 @@
+use crate::re::{is_extra, split_filename, Layout, Preset, Rule, RuleMatch, RuleMode, RuleSet, Scope, Template};
+
+/// 按作用范围拆分好的规则集，对种子名称与文件路径的各个部分分别应用对应的规则
+#[derive(Debug, Clone)]
+pub struct Renamer {
+    name: RuleSet,
+    top_folder: RuleSet,
+    folder: RuleSet,
+    stem: RuleSet,
+    extension: RuleSet,
+    /// 规则替换之后按模板重新生成名称，以及模板作用的范围
+    template: Option<(Template, Vec<Scope>)>,
+    /// 按媒体服务器预设重新安排文件的目录结构
+    preset: Option<Preset>,
+}
+
+/// 名称或路径中的一个部分上各条规则的匹配位置
+#[derive(Debug, Clone)]
+pub struct PartTrace {
+    pub scope: Scope,
+    pub matches: Vec<RuleMatch>,
+}
+
+impl Renamer {
+    pub fn new(rules: &[Rule], mode: RuleMode) -> Self {
+        Self {
+            name: RuleSet::new(rules, mode, &[Scope::Name]),
+            top_folder: RuleSet::new(rules, mode, &[Scope::TopFolder, Scope::Folder]),
+            folder: RuleSet::new(rules, mode, &[Scope::Folder]),
+            stem: RuleSet::new(rules, mode, &[Scope::Stem]),
+            extension: RuleSet::new(rules, mode, &[Scope::Extension]),
+            template: None,
+            preset: None,
+        }
+    }
+
+    /// 在规则替换之后，对 `scopes` 中的部分按发布名称解析并用模板重新生成，规则相当于解析前的预清理
+    pub fn with_template(self, template: Template, scopes: &[Scope]) -> Self {
+        Self { template: Some((template, scopes.to_vec())), ..self }
+    }
+
+    /// 按媒体服务器预设安排文件路径，此时计划按文件的完整路径生成，规则只作为解析前的预清理
+    pub fn with_preset(self, preset: Preset) -> Self {
+        Self { preset: Some(preset), ..self }
+    }
+
+    pub fn has_preset(&self) -> bool {
+        self.preset.is_some()
+    }
+
+    /// 按预设给出文件的新路径，`context` 为文件所在的顶层目录或种子名称，分别先经过主名与顶层目录的规则；
+    /// 样片、花絮等附加内容（按文件名或所在目录判断）保持原位
+    pub fn layout_file(&self, path: &str, context: &str) -> Option<Layout> {
+        let preset = self.preset?;
+        let mut components: Vec<&str> = path.split('/').collect();
+        let file_name = components.pop().unwrap_or_default();
+        let (stem, ext) = split_filename(file_name);
+        if is_extra(&stem) || components.iter().any(|dir| is_extra(dir)) {
+            return None;
+        }
+        preset.layout(&self.stem.replace(&stem), &ext, &self.top_folder.replace(context), file_name)
+    }
+
+    /// 部分属于模板的作用范围且能解析出元数据时按模板生成，否则保持规则替换的结果
+    fn apply_template(&self, scope: Scope, text: String) -> String {
+        match &self.template {
+            // 与规则一致，folder 范围也包括第一级目录
+            Some((template, scopes)) if scopes.contains(&scope) || scope == Scope::TopFolder && scopes.contains(&Scope::Folder) => {
+                template.apply(&text).unwrap_or(text)
+            }
+            _ => text,
+        }
+    }
+
+    /// 对种子名称应用规则
+    pub fn rename_torrent(&self, name: &str) -> String {
+        self.apply_template(Scope::Name, self.name.replace(name))
+    }
+
+    /// 对路径中第 `depth` 级（从 0 开始）的目录名应用规则
+    pub fn rename_folder(&self, name: &str, depth: usize) -> String {
+        let (scope, rule_set) = if depth == 0 { (Scope::TopFolder, &self.top_folder) } else { (Scope::Folder, &self.folder) };
+        self.apply_template(scope, rule_set.replace(name))
+    }
+
+    /// 对以 `/` 分隔的文件相对路径应用规则，目录、主名与扩展名分别使用各自范围内的规则
+    pub fn rename_file(&self, path: &str) -> String {
+        let parts = self
+            .file_parts(path)
+            .into_iter()
+            .map(|(scope, rule_set, text)| (scope, self.apply_template(scope, rule_set.replace(&text))));
+        join_parts(parts)
+    }
+
+    /// 记录种子名称上每条规则的匹配位置
+    pub fn trace_torrent(&self, name: &str) -> PartTrace {
+        PartTrace { scope: Scope::Name, matches: self.name.matches(name) }
+    }
+
+    /// 记录文件路径每个部分上每条规则的匹配位置
+    pub fn trace_file(&self, path: &str) -> Vec<PartTrace> {
+        self.file_parts(path)
+            .into_iter()
+            .map(|(scope, rule_set, text)| PartTrace { scope, matches: rule_set.matches(&text) })
+            .collect()
+    }
+
+    /// 将文件路径拆分为各级目录、主名与扩展名，并给出各自适用的规则集
+    fn file_parts(&self, path: &str) -> Vec<(Scope, &RuleSet, String)> {
+        let mut components: Vec<&str> = path.split('/').collect();
+        let file_name = components.pop().unwrap_or_default();
+
+        let mut parts: Vec<(Scope, &RuleSet, String)> = components
+            .into_iter()
+            .enumerate()
+            .map(|(depth, component)| match depth {
+                0 => (Scope::TopFolder, &self.top_folder, component.to_string()),
+                _ => (Scope::Folder, &self.folder, component.to_string()),
+            })
+            .collect();
+        let (stem, ext) = split_filename(file_name);
+        parts.push((Scope::Stem, &self.stem, stem));
+        parts.push((Scope::Extension, &self.extension, ext));
+        parts
+    }
+}
+
+/// 将替换后的各个部分重新组合为路径，扩展名为空时省略点
+fn join_parts(parts: impl IntoIterator<Item = (Scope, String)>) -> String {
+    let mut path = String::new();
+    for (scope, text) in parts {
+        match scope {
+            Scope::Extension if text.is_empty() => {}
+            Scope::Extension => {
+                path.push('.');
+                path.push_str(&text);
+            }
+            Scope::Stem | Scope::Name => path.push_str(&text),
+            Scope::TopFolder | Scope::Folder => {
+                path.push_str(&text);
+                path.push('/');
+            }
+        }
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_13_02_17() {
+        let scoped = |pattern: &str, replacement: &str, scope: &[Scope]| Rule {
+            scope: scope.to_vec(),
+            ..Rule::new(pattern, replacement)
+        };
+        let rules = [
+            scoped(r"^\[AD\]", "", &[Scope::TopFolder]),
+            scoped(r"\.", " ", &[Scope::Name, Scope::Stem]),
+            scoped("^MKV$", "mkv", &[Scope::Extension]),
+        ];
+        let renamer = Renamer::new(&rules, RuleMode::Compound);
+
+        assert_eq!(renamer.rename_torrent("[AD]Show.S01"), "[AD]Show S01");
+        assert_eq!(
+            renamer.rename_file("[AD]Show.S01/[AD]Extras.Disc/Show.S01E01.MKV"),
+            "Show.S01/[AD]Extras.Disc/Show S01E01.mkv"
+        );
+        assert_eq!(renamer.rename_file("no.folder.txt"), "no folder.txt");
+
+        // 默认作用范围与此前一致：名称、各级目录与主名，不含扩展名
+        let renamer = Renamer::new(&[Rule::new(r"\.", " ")], RuleMode::Compound);
+        assert_eq!(renamer.rename_file("a.b/c.d.tar.gz"), "a b/c d.tar.gz");
+    }
+}
@@ -1,67 +1,374 @@
 use crate::logger::LogUnwrap;
+use crate::re::{Rule, RuleKind, Scope};
+use clap::ValueEnum;
 use regex::{Captures, Regex, Replacer};
-use std::borrow::Borrow;
+use regex_syntax::ast::{self, Ast, Visitor};
 
 /// 一种支持多个正则表达式替换的替换器
 #[derive(Debug, Clone)]
 pub struct CompoundReplacer {
     compound_re: Regex,
-    group_names: Vec<String>,
-    replacements: Vec<String>,
+    /// 每条规则外层分组 `_groupN` 在复合正则中的组号
+    group_indexes: Vec<usize>,
+    replacements: Vec<Replacement>,
+}
+
+/// 常见的分辨率数值，出现在方括号中时不是集数
+const RESOLUTIONS: [u32; 6] = [240, 360, 480, 540, 576, 720];
+
+/// 一条规则匹配后的替换方式
+#[derive(Debug, Clone, PartialEq)]
+enum Replacement {
+    Template(Vec<Segment>),
+    /// 集数规范化，各字段为对应命名分组的全局组号
+    Episode {
+        seasons: Vec<usize>,
+        episodes: Vec<usize>,
+        /// 方括号中的集数，分辨率的数值不视为集数
+        brackets: Vec<usize>,
+        prefixes: Vec<usize>,
+        padding: usize,
+        default_season: u32,
+    },
+}
+
+/// 预编译的替换模板片段，组号已映射为复合正则中的全局组号
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Literal(String),
+    Group(usize),
 }
 
 impl CompoundReplacer {
+    /// 由 (正则, 替换模板) 直接构造，规则均为正则替换
+    #[cfg(test)]
     pub fn new<I, T, K, V>(pairs: I) -> Self
     where
         I: IntoIterator<Item = T>,
-        T: Borrow<(K, V)>,
+        T: std::borrow::Borrow<(K, V)>,
         K: AsRef<str>,
         V: AsRef<str>,
     {
-        let (patterns, replacements): (Vec<String>, Vec<String>) = pairs
-            .into_iter()
-            .map(|t| {
-                let (k, v) = t.borrow();
-                (k.as_ref().to_string(), v.as_ref().to_string())
-            })
-            .unzip();
+        Self::compile(pairs.into_iter().map(|t| {
+            let (k, v) = t.borrow();
+            (k.as_ref().to_string(), v.as_ref().to_string(), RuleKind::Regex)
+        }))
+    }
+
+    pub fn from_rules(rules: &[Rule]) -> Self {
+        Self::compile(rules.iter().map(|r| (r.regex(), r.replacement.clone(), r.kind)))
+    }
+
+    fn compile(rules: impl IntoIterator<Item = (String, String, RuleKind)>) -> Self {
+        let mut patterns = vec![];
+        let mut replacements = vec![];
+        let mut kinds = vec![];
+        for (pattern, replacement, kind) in rules {
+            patterns.push(pattern);
+            replacements.push(replacement);
+            kinds.push(kind);
+        }
 
         let group_names: Vec<String> = (0..patterns.len())
             .map(|i| format!("_group{}", i))
             .collect();
+        // 用户的命名分组在复合正则中可能重名，需要加上规则序号前缀
         let regex_str = patterns
             .iter()
             .enumerate()
-            .map(|(i, pat)| format!("(?P<{}>{})", group_names[i], pat))
+            .map(|(i, pat)| format!("(?P<{}>{})", group_names[i], prefix_group_names(pat, i)))
             .collect::<Vec<_>>()
             .join("|");
 
         let compound_re = Regex::new(&regex_str).log_unwrap(&format!("Invalid regex: {}", regex_str));
 
+        let names: Vec<Option<&str>> = compound_re.capture_names().collect();
+        let group_indexes: Vec<usize> = group_names
+            .iter()
+            .map(|g| names.iter().position(|n| *n == Some(g.as_str())).unwrap())
+            .collect();
+        let replacements = replacements
+            .iter()
+            .enumerate()
+            .map(|(i, rep)| {
+                // 规则自身的分组位于外层分组与下一条规则的外层分组之间
+                let start = group_indexes[i];
+                let end = group_indexes.get(i + 1).copied().unwrap_or(names.len());
+                match kinds[i] {
+                    RuleKind::Regex => Replacement::Template(compile_replacement(rep, i, start, end, &names)),
+                    RuleKind::Episode { padding, default_season } => {
+                        // 内置模式中按名称前缀区分季、集与前缀分组
+                        let groups = |kind: &str| {
+                            let prefix = format!("_r{}_{}", i, kind);
+                            (start..end).filter(|&g| names[g].is_some_and(|n| n.starts_with(&prefix))).collect()
+                        };
+                        Replacement::Episode {
+                            seasons: groups("season"),
+                            episodes: groups("episode"),
+                            brackets: groups("bracket"),
+                            prefixes: groups("prefix"),
+                            padding,
+                            default_season,
+                        }
+                    }
+                }
+            })
+            .collect();
+
         Self {
             compound_re,
-            group_names,
+            group_indexes,
             replacements,
         }
     }
 
     pub fn replace(&self, text: &str) -> String {
-        struct GroupReplacer<'a>(&'a [String], &'a [String]);
+        struct GroupReplacer<'a>(&'a [usize], &'a [Replacement]);
 
         impl Replacer for GroupReplacer<'_> {
             fn replace_append(&mut self, caps: &Captures, dst: &mut String) {
-                for (name, rep) in self.0.iter().zip(self.1.iter()) {
-                    if caps.name(name).is_some() {
-                        dst.push_str(rep);
-                        return;
+                for (&index, rep) in self.0.iter().zip(self.1.iter()) {
+                    if caps.get(index).is_none() {
+                        continue;
+                    }
+                    match rep {
+                        Replacement::Template(segments) => {
+                            for segment in segments {
+                                match segment {
+                                    Segment::Literal(s) => dst.push_str(s),
+                                    Segment::Group(i) => dst.push_str(caps.get(*i).map_or("", |m| m.as_str())),
+                                }
+                            }
+                        }
+                        Replacement::Episode { seasons, episodes, brackets, prefixes, padding, default_season } => {
+                            let first = |groups: &[usize]| groups.iter().find_map(|&g| caps.get(g)).map(|m| m.as_str());
+                            let bracket = first(brackets).and_then(|e| e.parse().ok()).filter(|e| !RESOLUTIONS.contains(e));
+                            let season = match first(seasons) {
+                                Some(s) => s.parse().ok(),
+                                None => Some(*default_season),
+                            };
+                            let episode = first(episodes).and_then(|e| e.parse::<u32>().ok()).or(bracket);
+                            // 无法解析的数字保持原样，而不是写成 E00
+                            let (Some(season), Some(episode)) = (season, episode) else {
+                                dst.push_str(&caps[0]);
+                                return;
+                            };
+                            dst.push_str(first(prefixes).unwrap_or_default());
+                            dst.push_str(&format!("S{:0w$}E{:0w$}", season, episode, w = padding));
+                        }
                     }
+                    return;
                 }
                 dst.push_str(&caps[0]);
             }
         }
 
-        self.compound_re.replace_all(text, GroupReplacer(&self.group_names, &self.replacements)).into_owned()
+        self.compound_re.replace_all(text, GroupReplacer(&self.group_indexes, &self.replacements)).into_owned()
+    }
+
+    /// 与 `replace` 相同的匹配过程，返回每处匹配对应的规则序号与字节范围
+    pub fn matches(&self, text: &str) -> Vec<RuleMatch> {
+        self.compound_re
+            .captures_iter(text)
+            .filter_map(|caps| {
+                let rule = self.group_indexes.iter().position(|&i| caps.get(i).is_some())?;
+                let m = caps.get(0)?;
+                Some(RuleMatch { rule, start: m.start(), end: m.end(), text: m.as_str().to_string() })
+            })
+            .collect()
+    }
+}
+
+/// 一处规则匹配，`start..end` 为规则所见文本中的字节范围
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleMatch {
+    pub rule: usize,
+    pub start: usize,
+    pub end: usize,
+    pub text: String,
+}
+
+/// 按顺序逐条应用规则的替换器，第 N 条规则作用于第 N-1 条规则的输出
+#[derive(Debug, Clone)]
+pub struct SequentialReplacer {
+    steps: Vec<CompoundReplacer>,
+}
+
+impl SequentialReplacer {
+    pub fn from_rules(rules: &[Rule]) -> Self {
+        let steps = rules
+            .iter()
+            .map(|rule| CompoundReplacer::from_rules(std::slice::from_ref(rule)))
+            .collect();
+        Self { steps }
     }
+
+    pub fn replace(&self, text: &str) -> String {
+        self.steps
+            .iter()
+            .fold(text.to_string(), |acc, step| step.replace(&acc))
+    }
+
+    /// 每条规则的匹配范围相对于该规则执行时的输入，即上一条规则的输出
+    pub fn matches(&self, text: &str) -> Vec<RuleMatch> {
+        let mut text = text.to_string();
+        let mut matches = vec![];
+        for (i, step) in self.steps.iter().enumerate() {
+            matches.extend(step.matches(&text).into_iter().map(|m| RuleMatch { rule: i, ..m }));
+            text = step.replace(&text);
+        }
+        matches
+    }
+}
+
+/// 规则的应用方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum RuleMode {
+    /// 所有规则合并为一个正则，在每个位置上靠前的规则优先
+    #[default]
+    Compound,
+    /// 规则按顺序依次执行，后面的规则能看到前面规则的输出
+    Sequential,
+}
+
+/// 按指定模式编译好的一组规则，只包含作用于给定范围且已启用的规则
+#[derive(Debug, Clone)]
+pub struct RuleSet {
+    engine: Engine,
+    /// 编译后的规则在原始规则列表中的位置
+    indexes: Vec<usize>,
+}
+
+#[derive(Debug, Clone)]
+enum Engine {
+    Compound(CompoundReplacer),
+    Sequential(SequentialReplacer),
+}
+
+impl RuleSet {
+    pub fn new(rules: &[Rule], mode: RuleMode, scopes: &[Scope]) -> Self {
+        let (indexes, rules): (Vec<usize>, Vec<Rule>) = rules
+            .iter()
+            .enumerate()
+            .filter(|(_, r)| r.enabled && r.applies_to(scopes))
+            .map(|(i, r)| (i, r.clone()))
+            .unzip();
+        let engine = match mode {
+            RuleMode::Compound => Engine::Compound(CompoundReplacer::from_rules(&rules)),
+            RuleMode::Sequential => Engine::Sequential(SequentialReplacer::from_rules(&rules)),
+        };
+        Self { engine, indexes }
+    }
+
+    pub fn replace(&self, text: &str) -> String {
+        match &self.engine {
+            Engine::Compound(r) => r.replace(text),
+            Engine::Sequential(r) => r.replace(text),
+        }
+    }
+
+    /// 返回每处匹配，规则序号为其在原始规则列表中的位置
+    pub fn matches(&self, text: &str) -> Vec<RuleMatch> {
+        let matches = match &self.engine {
+            Engine::Compound(r) => r.matches(text),
+            Engine::Sequential(r) => r.matches(text),
+        };
+        matches
+            .into_iter()
+            .map(|m| RuleMatch { rule: self.indexes[m.rule], ..m })
+            .collect()
+    }
+}
+
+/// 为第 `rule` 条规则中的命名分组加上前缀 `_r{rule}_`，避免与其他规则重名
+fn prefix_group_names(pattern: &str, rule: usize) -> String {
+    struct NameOffsets(Vec<usize>);
+
+    impl Visitor for NameOffsets {
+        type Output = Vec<usize>;
+        type Err = ();
+
+        fn finish(self) -> Result<Self::Output, Self::Err> {
+            Ok(self.0)
+        }
+
+        fn visit_pre(&mut self, ast: &Ast) -> Result<(), Self::Err> {
+            if let Ast::Group(group) = ast
+                && let ast::GroupKind::CaptureName { name, .. } = &group.kind
+            {
+                self.0.push(name.span.start.offset);
+            }
+            Ok(())
+        }
+    }
+
+    // 解析失败时原样返回，由后续编译报告错误
+    let Ok(ast) = ast::parse::Parser::new().parse(pattern) else {
+        return pattern.to_string();
+    };
+    let offsets = ast::visit(&ast, NameOffsets(vec![])).unwrap_or_default();
+
+    let mut result = pattern.to_string();
+    for start in offsets.into_iter().rev() {
+        result.insert_str(start, &format!("_r{}_", rule));
+    }
+    result
+}
+
+/// 将替换模板编译为片段，支持 `$1` `${1}` `$name` `${name}` 与 `$$`
+/// 组号仅在规则自身的分组范围 `[start, end)` 内解析，`$0` 指整条规则的匹配，无效引用替换为空串
+fn compile_replacement(rep: &str, rule: usize, start: usize, end: usize, names: &[Option<&str>]) -> Vec<Segment> {
+    let resolve = |name: &str| -> Option<usize> {
+        match name.parse::<usize>() {
+            Ok(i) => (start + i < end).then_some(start + i),
+            Err(_) => {
+                let prefixed = format!("_r{}_{}", rule, name);
+                (start..end).find(|&i| names[i] == Some(prefixed.as_str()))
+            }
+        }
+    };
+
+    let mut segments = vec![];
+    let mut literal = String::new();
+    let mut rest = rep;
+    while let Some(pos) = rest.find('$') {
+        literal.push_str(&rest[..pos]);
+        rest = &rest[pos + 1..];
+
+        let (name, remain) = if rest.starts_with('$') {
+            literal.push('$');
+            rest = &rest[1..];
+            continue;
+        } else if let Some(braced) = rest.strip_prefix('{') {
+            match braced.find('}') {
+                Some(close) => (&braced[..close], &braced[close + 1..]),
+                // 未闭合的大括号按字面量处理
+                None => ("", rest),
+            }
+        } else {
+            let len = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (&rest[..len], &rest[len..])
+        };
+
+        if name.is_empty() {
+            literal.push('$');
+            continue;
+        }
+        rest = remain;
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(std::mem::take(&mut literal)));
+        }
+        if let Some(index) = resolve(name) {
+            segments.push(Segment::Group(index));
+        }
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    segments
 }
 
 #[cfg(test)]
@@ -90,4 +397,102 @@ mod tests {
             "小丑2：双重妄想[HDR+杜比视界双版本][中文字幕] 2024 2160p UHD BluRay Remux DV HEVC TrueHD7 1-ParkHD"
         );
     }
+
+    #[test]
+    fn test_2026_10_18_09_12_40() {
+        fn compound_replacement(text: &str, replacer: &[(&str, &str)]) -> String {
+            let replacer = CompoundReplacer::new(replacer);
+            replacer.replace(text)
+        }
+
+        assert_eq!(
+            compound_replacement("Movie.2024.1080p", &[(r"\.(\d{4})\.(\d{3,4}p)", " $2 ($1)")]),
+            "Movie 1080p (2024)"
+        );
+        // 每条规则的组号相互独立
+        assert_eq!(
+            compound_replacement("ab-12", &[(r"([a-z])([a-z])", "$2$1"), (r"(\d)(\d)", "${2}${1}")]),
+            "ba-21"
+        );
+        // 命名分组允许在不同规则中重名
+        assert_eq!(
+            compound_replacement(
+                "E01 x2",
+                &[(r"E(?P<n>\d+)", "Ep${n}"), (r"x(?<n>\d)", "$n$0")]
+            ),
+            "Ep01 2x2"
+        );
+        // `$$` 转义、无效引用为空、未闭合的大括号保留字面量
+        assert_eq!(compound_replacement("a", &[("(a)", "$$1 $9 ${x")]), "$1  ${x");
+        assert_eq!(compound_replacement("a", &[("a", "$ $")]), "$ $");
+    }
+
+    #[test]
+    fn test_2026_10_18_10_05_21() {
+        let rules = [
+            Rule::new(r"^\[AD\]", ""),
+            Rule::new(r"\.", " "),
+            Rule::new(r" {2,}", " "),
+        ];
+        let text = "[AD]Some.Show..S01";
+
+        // 复合模式下规则互相看不到彼此的输出
+        let compound = RuleSet::new(&rules, RuleMode::Compound, &[Scope::Name]);
+        let sequential = RuleSet::new(&rules, RuleMode::Sequential, &[Scope::Name]);
+        assert_eq!(compound.replace(text), "Some Show  S01");
+        assert_eq!(sequential.replace(text), "Some Show S01");
+
+        let m = |rule, start, end, text: &str| RuleMatch { rule, start, end, text: text.into() };
+        assert_eq!(
+            compound.matches(text),
+            [m(0, 0, 4, "[AD]"), m(1, 8, 9, "."), m(1, 13, 14, "."), m(1, 14, 15, ".")]
+        );
+        // 顺序模式下的范围相对于上一条规则的输出
+        assert_eq!(
+            sequential.matches(text),
+            [m(0, 0, 4, "[AD]"), m(1, 4, 5, "."), m(1, 9, 10, "."), m(1, 10, 11, "."), m(2, 9, 11, "  ")]
+        );
+
+        // 禁用或不在作用范围内的规则不参与替换，序号仍对应原始位置
+        let rules = [
+            Rule { enabled: false, ..Rule::new("a", "1") },
+            Rule { scope: vec![Scope::Extension], ..Rule::new("b", "2") },
+            Rule::new("c", "3"),
+        ];
+        let rule_set = RuleSet::new(&rules, RuleMode::Compound, &[Scope::Stem]);
+        assert_eq!(rule_set.replace("abc"), "ab3");
+        assert_eq!(rule_set.matches("abc"), [m(2, 2, 3, "c")]);
+    }
+
+    #[test]
+    fn test_2026_10_18_22_14_31() {
+        let rules = [Rule::episode(2, 1), Rule::new(r"\.", " ")];
+        let rule_set = RuleSet::new(&rules, RuleMode::Compound, &[Scope::Stem]);
+        let names = [
+            ("[Sub] Show [01][1080p]", "[Sub] Show S01E01[1080p]"),
+            ("某剧 第2季第05集", "某剧 S02E05"),
+            ("某剧.第12集", "某剧 S01E12"),
+            ("Show.EP03.720p", "Show S01E03 720p"),
+            ("[Sub] Show - 07v2 (1080p)", "[Sub] Show - S01E07 (1080p)"),
+            ("Show.2x05", "Show S02E05"),
+            ("show.s1e2", "show S01E02"),
+            ("Movie.2024.1080p", "Movie 2024 1080p"),
+            // 全角数字与方括号中的分辨率不是集数
+            ("Show.第１２集", "Show 第１２集"),
+            ("Show [720]", "Show [720]"),
+            ("Show [480][12]", "Show [480]S01E12"),
+            // 单独的 E 后面不能有分隔符，也不匹配四位的年份
+            ("Wall-E.2008.1080p", "Wall-E 2008 1080p"),
+            ("Part E 12", "Part E 12"),
+            ("Show.E2008", "Show E2008"),
+            ("Show.EP 12", "Show S01E12"),
+        ];
+        for (name, expected) in names {
+            assert_eq!(rule_set.replace(name), expected, "{}", name);
+        }
+        // 与其它规则的分组互不影响，补零宽度与默认季可配置
+        let rules = [Rule::new("(a)", "$1$1"), Rule::episode(3, 2)];
+        let rule_set = RuleSet::new(&rules, RuleMode::Sequential, &[Scope::Stem]);
+        assert_eq!(rule_set.replace("a E7"), "aa S002E007");
+    }
 }
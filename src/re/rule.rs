@@ -0,0 +1,93 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+
+/// 规则的作用范围
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Scope {
+    /// 种子在客户端中显示的名称
+    Name,
+    /// 文件路径中的第一级目录
+    TopFolder,
+    /// 文件路径中的每一级目录，包括第一级
+    Folder,
+    /// 文件主名，不含扩展名
+    Stem,
+    /// 文件扩展名，不含点
+    Extension,
+}
+
+/// 未指定作用范围时的默认值，不修改扩展名
+pub const DEFAULT_SCOPE: &[Scope] = &[Scope::Name, Scope::Folder, Scope::Stem];
+
+/// 内置的集数写法：`S1E2`、`1x02`、`第2季第02集`、`EP02`、`E02`、`[02]`、` - 02`，
+/// 命名分组 `seasonN`、`episodeN` 为季与集，`prefixN` 为需要保留的前缀，
+/// `bracketN` 为方括号中的集数，可能是 `[720]` 这样的分辨率，替换时单独判断。
+/// 单独的 `E` 后必须紧跟不超过三位的数字，避免 `Wall-E.2008` 被当作集数；
+/// 数字只匹配 ASCII，`\d` 会匹配无法解析的全角数字
+pub const EPISODE_PATTERN: &str = concat!(
+    r"(?i:\bS(?P<season0>[0-9]{1,2})[ ._-]?E(?P<episode0>[0-9]{1,4})\b)",
+    r"|\b(?P<season1>[0-9]{1,2})x(?P<episode1>[0-9]{2,3})\b",
+    r"|(?:第(?P<season2>[0-9]{1,3})季[ ._-]?)?第(?P<episode2>[0-9]{1,4})[集话話]",
+    r"|(?i:\bEP[ .]?(?P<episode3>[0-9]{1,4})\b)",
+    r"|(?i:\bE(?P<episode4>[0-9]{1,3})\b)",
+    r"|\[(?P<bracket5>[0-9]{1,3})(?:v[0-9])?\]",
+    r"|(?P<prefix6>\s-\s)(?P<episode6>[0-9]{1,3})(?:v[0-9])?\b",
+);
+
+/// 规则的类型
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RuleKind {
+    /// 正则表达式替换
+    #[default]
+    Regex,
+    /// 把各种集数写法统一为 `S01E01`，季与集补零到 `padding` 位，没有季时使用 `default_season`
+    Episode { padding: usize, default_season: u32 },
+}
+
+/// 一条重命名规则，由正则表达式与替换模板组成
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rule {
+    pub pattern: String,
+    pub replacement: String,
+    /// 是否忽略大小写
+    pub case_insensitive: bool,
+    /// 禁用的规则不参与替换
+    pub enabled: bool,
+    pub description: String,
+    pub scope: Vec<Scope>,
+    pub kind: RuleKind,
+}
+
+impl Rule {
+    pub fn new(pattern: impl Into<String>, replacement: impl Into<String>) -> Self {
+        Self {
+            pattern: pattern.into(),
+            replacement: replacement.into(),
+            case_insensitive: false,
+            enabled: true,
+            description: String::new(),
+            scope: DEFAULT_SCOPE.to_vec(),
+            kind: RuleKind::Regex,
+        }
+    }
+
+    /// 集数规范化规则，使用内置的 [`EPISODE_PATTERN`]
+    pub fn episode(padding: usize, default_season: u32) -> Self {
+        Self { kind: RuleKind::Episode { padding, default_season }, ..Self::new(EPISODE_PATTERN, "") }
+    }
+
+    /// 规则是否作用于任一给定范围
+    pub fn applies_to(&self, scopes: &[Scope]) -> bool {
+        self.scope.iter().any(|s| scopes.contains(s))
+    }
+
+    /// 实际参与编译的正则表达式，已应用规则上的标志
+    pub fn regex(&self) -> String {
+        if self.case_insensitive {
+            format!("(?i:{})", self.pattern)
+        } else {
+            self.pattern.clone()
+        }
+    }
+}
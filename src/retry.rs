@@ -0,0 +1,87 @@
+use crate::log;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::OnceLock;
+use std::time::Duration;
+
+/// 第一次重试前的等待时间，之后每次翻倍
+const BASE_DELAY: Duration = Duration::from_millis(500);
+/// 两次重试之间的最长等待时间
+const MAX_DELAY: Duration = Duration::from_secs(10);
+
+// 全局变量存储请求失败后的最大重试次数
+static MAX_RETRIES: OnceLock<u32> = OnceLock::new();
+
+// 设置请求失败后的最大重试次数
+pub fn set_max_retries(retries: u32) {
+    MAX_RETRIES.get_or_init(|| retries);
+}
+
+/// 发送只读等可以安全重复的请求，连接失败、超时、429 或 5xx 时按带抖动的指数退避重试
+pub async fn send(request: RequestBuilder) -> reqwest::Result<Response> {
+    // 只读请求不会被当作已生效，因此总有响应
+    send_unless_applied(request, || async { false }).await.map(|resp| resp.unwrap())
+}
+
+/// 发送重命名等不能重复生效的请求。每次重试前先调用 `applied` 确认上一次请求是否其实已经生效
+/// （例如请求已被处理但响应超时），已生效时不再重试并返回 `None`
+pub async fn send_unless_applied<F: Future<Output = bool>>(
+    request: RequestBuilder,
+    applied: impl Fn() -> F,
+) -> reqwest::Result<Option<Response>> {
+    let max_retries = MAX_RETRIES.get().copied().unwrap_or(0);
+    let mut attempt = 0;
+    loop {
+        // 表单与查询参数都可以复制，只有流式请求体无法复制
+        let result = request
+            .try_clone()
+            .expect("request body can not be cloned")
+            .send()
+            .await
+            .and_then(|resp| resp.error_for_status());
+        match result {
+            Err(e) if attempt < max_retries && is_transient(&e) => {
+                let delay = backoff(attempt);
+                attempt += 1;
+                log!("Request failed ({}), retry {}/{} in {}", e, attempt, max_retries, humantime::format_duration(delay));
+                tokio::time::sleep(delay).await;
+                if applied().await {
+                    return Ok(None);
+                }
+            }
+            result => return result.map(Some),
+        }
+    }
+}
+
+/// 连接失败、超时、请求被限流或服务器内部错误时可以重试，其它错误重试也不会成功
+fn is_transient(e: &reqwest::Error) -> bool {
+    match e.status() {
+        Some(status) => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+        None => e.is_timeout() || e.is_connect() || e.is_request(),
+    }
+}
+
+/// 第 `attempt` 次重试前的等待时间，在指数退避的基础上随机取其一半到全部，避免并发请求同时重试
+fn backoff(attempt: u32) -> Duration {
+    let delay = BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(MAX_DELAY);
+    let random = RandomState::new().build_hasher().finish();
+    let half = delay.as_millis() as u64 / 2;
+    Duration::from_millis(half + random % (half + 1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_17_08_33() {
+        for attempt in 0..20 {
+            let delay = backoff(attempt);
+            let full = BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(MAX_DELAY);
+            assert!(delay >= full / 2 && delay <= full, "{:?} {:?}", delay, full);
+        }
+        assert!(backoff(30) <= MAX_DELAY);
+    }
+}
@@ -0,0 +1,311 @@
+use crate::error::Error;
+use crate::log;
+use crate::re::{Rule, Scope, DEFAULT_SCOPE};
+use regex::Regex;
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashSet};
+use toml::Spanned;
+
+/// TOML 规则文件
+/// ```toml
+/// # 未指定规则组时依次展开的规则组，随后是顶层规则
+/// use = ["ads"]
+///
+/// [[rules]]
+/// pattern = '\.'
+/// replacement = " "
+/// description = "点替换为空格"
+/// scope = ["name", "stem"]
+///
+/// # 集数规范化规则使用内置模式，把 [01]、第01集、EP01、1x01 等写法统一为 S01E01
+/// [[rules]]
+/// type = "episode"
+/// padding = 2
+/// default_season = 1
+///
+/// [groups.ads]
+/// include = ["other-group"]
+/// [[groups.ads.rules]]
+/// pattern = '[\[【].*?(电影|高清|发布).*?[】\]]'
+/// case_insensitive = true
+/// enabled = true
+/// ```
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RulesFile {
+    #[serde(default, rename = "use")]
+    use_groups: Vec<Spanned<String>>,
+    #[serde(default)]
+    rules: Vec<RuleEntry>,
+    #[serde(default)]
+    groups: BTreeMap<String, GroupEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct GroupEntry {
+    #[serde(default)]
+    include: Vec<Spanned<String>>,
+    #[serde(default)]
+    rules: Vec<RuleEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct RuleEntry {
+    #[serde(default, rename = "type")]
+    kind: RuleType,
+    /// 集数规则使用内置模式，不需要 pattern
+    pattern: Option<Spanned<String>>,
+    #[serde(default)]
+    replacement: String,
+    #[serde(default)]
+    case_insensitive: bool,
+    #[serde(default = "default_enabled")]
+    enabled: bool,
+    #[serde(default)]
+    description: String,
+    #[serde(default = "default_scope")]
+    scope: Vec<Scope>,
+    /// 以下只用于集数规则
+    padding: Option<usize>,
+    default_season: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+enum RuleType {
+    #[default]
+    Regex,
+    Episode,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+fn default_scope() -> Vec<Scope> {
+    DEFAULT_SCOPE.to_vec()
+}
+
+/// 解析命令行中的规则参数，每个参数可以是：
+/// - `pattern=replacement` 形式的单条规则
+/// - 纯文本规则文件路径，文件内每两行视为一条规则
+/// - `.toml` 规则文件路径，可用 `path.toml#group` 只加载指定的规则组
+pub fn load_rules(args: &[String]) -> Result<Vec<Rule>, Error> {
+    let mut rules = vec![];
+    for arg in args {
+        // 指向文件时允许带 file:// 前缀
+        let path = arg.strip_prefix("file://").unwrap_or(arg);
+        let (file, group) = match path.rsplit_once('#') {
+            Some((file, group)) if file.ends_with(".toml") => (file, Some(group)),
+            _ => (path, None),
+        };
+
+        if file.ends_with(".toml") && (!arg.contains('=') || std::path::Path::new(file).is_file()) {
+            let content = std::fs::read_to_string(file)
+                .map_err(|e| Error::InvalidRules(format!("Failed to read rules file {}: {}", file, e)))?;
+            rules.extend(parse_toml_rules(&content, group).map_err(|e| Error::InvalidRules(format!("{}: {}", file, e)))?);
+        } else if let Some((p, r)) = arg.rsplit_once('=') {
+            rules.push(Rule::new(p, r));
+        } else {
+            // 如果没有等号，则认为是文件路径
+            let content = std::fs::read_to_string(file)
+                .map_err(|e| Error::InvalidRules(format!("Failed to read rules file {}: {}", file, e)))?;
+            rules.extend(parse_text_rules(&content, file));
+        }
+    }
+
+    for rule in &rules {
+        Regex::new(&rule.regex()).map_err(|e| Error::InvalidRules(format!("Invalid regex {:?}: {}", rule.pattern, e)))?;
+    }
+    Ok(rules)
+}
+
+/// 解析纯文本规则，每两行视为一条规则，末尾多出的一行会被忽略并给出警告
+fn parse_text_rules(content: &str, file: &str) -> Vec<Rule> {
+    let lines: Vec<&str> = content.lines().collect();
+    let (pairs, rest) = lines.as_chunks::<2>();
+    if let [line] = rest {
+        log!("Warning: ignoring unpaired last line {} in {}: {:?}", lines.len(), file, line);
+    }
+    pairs.iter().map(|[p, r]| Rule::new(*p, *r)).collect()
+}
+
+/// 解析 TOML 规则，错误信息中包含出错位置的行号与列号
+fn parse_toml_rules(content: &str, group: Option<&str>) -> Result<Vec<Rule>, String> {
+    // toml 的解析错误自带行列信息
+    let file: RulesFile = toml::from_str(content).map_err(|e| e.to_string())?;
+    let at = |span: std::ops::Range<usize>| {
+        let (line, column) = line_column(content, span.start);
+        format!("line {}, column {}", line, column)
+    };
+
+    let mut rules = vec![];
+    let mut expanded = HashSet::new();
+    match group {
+        Some(name) => {
+            if !file.groups.contains_key(name) {
+                return Err(format!("Unknown rule group {:?}", name));
+            }
+            expand_group(&file, name, &mut vec![], &mut expanded, &mut rules, &at)?;
+        }
+        None => {
+            for name in &file.use_groups {
+                if !file.groups.contains_key(name.get_ref()) {
+                    return Err(format!("Unknown rule group {:?} at {}", name.get_ref(), at(name.span())));
+                }
+                expand_group(&file, name.get_ref(), &mut vec![], &mut expanded, &mut rules, &at)?;
+            }
+            for entry in &file.rules {
+                rules.push(to_rule(entry, &at)?);
+            }
+        }
+    }
+    Ok(rules)
+}
+
+/// 递归展开规则组，先展开 include 的规则组，再追加自身的规则；
+/// 经多条路径到达的同一规则组（如 A 包含 B 与 C，两者都包含 D）只展开一次
+fn expand_group<'a>(
+    file: &'a RulesFile,
+    name: &'a str,
+    stack: &mut Vec<&'a str>,
+    expanded: &mut HashSet<&'a str>,
+    rules: &mut Vec<Rule>,
+    at: &impl Fn(std::ops::Range<usize>) -> String,
+) -> Result<(), String> {
+    if stack.contains(&name) {
+        return Err(format!("Rule group {:?} includes itself: {} -> {}", name, stack.join(" -> "), name));
+    }
+    if !expanded.insert(name) {
+        return Ok(());
+    }
+    let group = &file.groups[name];
+    stack.push(name);
+    for include in &group.include {
+        if !file.groups.contains_key(include.get_ref()) {
+            return Err(format!("Unknown rule group {:?} at {}", include.get_ref(), at(include.span())));
+        }
+        expand_group(file, include.get_ref(), stack, expanded, rules, at)?;
+    }
+    for entry in &group.rules {
+        rules.push(to_rule(entry, at)?);
+    }
+    stack.pop();
+    Ok(())
+}
+
+fn to_rule(entry: &RuleEntry, at: &impl Fn(std::ops::Range<usize>) -> String) -> Result<Rule, String> {
+    let rule = match (entry.kind, &entry.pattern) {
+        (RuleType::Regex, Some(pattern)) => {
+            if entry.padding.is_some() || entry.default_season.is_some() {
+                return Err(format!("padding and default_season only apply to episode rules, at {}", at(pattern.span())));
+            }
+            Rule::new(pattern.get_ref(), &entry.replacement)
+        }
+        (RuleType::Regex, None) => return Err(format!("Missing pattern in rule {:?}", entry.description)),
+        (RuleType::Episode, Some(pattern)) => {
+            return Err(format!("Episode rules use a built-in pattern, remove the pattern at {}", at(pattern.span())));
+        }
+        (RuleType::Episode, None) => Rule::episode(entry.padding.unwrap_or(2), entry.default_season.unwrap_or(1)),
+    };
+    let rule = Rule {
+        case_insensitive: entry.case_insensitive,
+        enabled: entry.enabled,
+        description: entry.description.clone(),
+        scope: entry.scope.clone(),
+        ..rule
+    };
+    if let (Err(e), Some(pattern)) = (Regex::new(&rule.regex()), &entry.pattern) {
+        let name = if rule.description.is_empty() { &rule.pattern } else { &rule.description };
+        return Err(format!("Invalid regex in rule {:?} at {}: {}", name, at(pattern.span()), e));
+    }
+    Ok(rule)
+}
+
+/// 将字节偏移转换为从 1 开始的行号与列号
+fn line_column(content: &str, offset: usize) -> (usize, usize) {
+    let before = &content[..offset];
+    let line = before.matches('\n').count() + 1;
+    let column = before.rsplit('\n').next().unwrap_or("").chars().count() + 1;
+    (line, column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::re::RuleKind;
+
+    #[test]
+    fn test_2026_10_18_11_20_44() {
+        let content = r#"
+use = ["tv"]
+
+[[rules]]
+pattern = '\.'
+replacement = " "
+
+[groups.ads]
+[[groups.ads.rules]]
+pattern = 'www\.\w+\.com'
+scope = ["top-folder", "name"]
+case_insensitive = true
+description = "ad links"
+
+[groups.tv]
+include = ["ads"]
+[[groups.tv.rules]]
+pattern = 'x264'
+enabled = false
+"#;
+        let rules = parse_toml_rules(content, None).unwrap();
+        let patterns: Vec<&str> = rules.iter().map(|r| r.pattern.as_str()).collect();
+        assert_eq!(patterns, [r"www\.\w+\.com", "x264", r"\."]);
+        assert!(rules[0].case_insensitive);
+        assert_eq!(rules[0].description, "ad links");
+        assert_eq!(rules[0].scope, [Scope::TopFolder, Scope::Name]);
+        assert_eq!(rules[2].scope, DEFAULT_SCOPE);
+        assert!(!rules[1].enabled);
+        assert_eq!(rules[2].replacement, " ");
+
+        let rules = parse_toml_rules(content, Some("ads")).unwrap();
+        assert_eq!(rules.len(), 1);
+
+        let err = parse_toml_rules("[[rules]]\npattern = 'a'\n\n[[rules]]\npattern = '(a'\n", None).unwrap_err();
+        assert!(err.contains("line 5, column 11"), "{}", err);
+        let err = parse_toml_rules("use = ['x']", None).unwrap_err();
+        assert!(err.contains("line 1, column 8"), "{}", err);
+        let err = parse_toml_rules("[[rules]]\ntype = 'episode'\npattern = 'a'\n", None).unwrap_err();
+        assert!(err.contains("line 3, column 11"), "{}", err);
+        let rules = parse_toml_rules("[[rules]]\ntype = 'episode'\npadding = 3\n", None).unwrap();
+        assert_eq!(rules[0].kind, RuleKind::Episode { padding: 3, default_season: 1 });
+        let err = parse_toml_rules("[groups.a]\ninclude = ['a']", Some("a")).unwrap_err();
+        assert!(err.contains("includes itself"), "{}", err);
+    }
+
+    #[test]
+    fn test_2026_10_18_11_31_02() {
+        let rules = parse_text_rules("a\n1\nb\n2\nc", "rules.txt");
+        assert_eq!(rules, [Rule::new("a", "1"), Rule::new("b", "2")]);
+
+        // 菱形包含：D 经 B 与 C 两条路径到达，其规则只出现一次
+        let content = r#"
+[groups.a]
+include = ["b", "c"]
+[groups.b]
+include = ["d"]
+[[groups.b.rules]]
+pattern = 'b'
+[groups.c]
+include = ["d"]
+[[groups.c.rules]]
+pattern = 'c'
+[groups.d]
+[[groups.d.rules]]
+pattern = 'd'
+"#;
+        let rules = parse_toml_rules(content, Some("a")).unwrap();
+        assert_eq!(rules.iter().map(|r| r.pattern.as_str()).collect::<Vec<_>>(), ["d", "b", "c"]);
+    }
+}
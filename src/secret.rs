@@ -0,0 +1,74 @@
+use crate::error::Error;
+use std::convert::Infallible;
+use std::path::Path;
+use std::str::FromStr;
+
+/// 用户名或密码，`Debug` 输出中只显示 `***`，避免出现在日志里
+#[derive(Clone)]
+pub struct Secret(String);
+
+impl Secret {
+    pub fn into_inner(self) -> String {
+        self.0
+    }
+}
+
+impl FromStr for Secret {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl std::fmt::Debug for Secret {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+/// 提供用户名的环境变量
+pub const USERNAME_ENV: &str = "TORRENT_TIDY_USERNAME";
+/// 提供密码的环境变量
+pub const PASSWORD_ENV: &str = "TORRENT_TIDY_PASSWORD";
+
+/// 按优先级取得凭据：标准输入、文件、命令行参数，最后是环境变量的值 `env`。
+/// 从标准输入读取时每个凭据读一行，同时读取用户名与密码时需先读用户名
+pub fn resolve(value: Option<Secret>, file: Option<&Path>, stdin: bool, env: Option<String>, what: &str) -> Result<Option<Secret>, Error> {
+    if stdin {
+        let mut line = String::new();
+        std::io::stdin()
+            .read_line(&mut line)
+            .map_err(|e| Error::Io(format!("Failed to read {} from stdin: {}", what, e)))?;
+        return Ok(Some(Secret(line.trim_end_matches(['\r', '\n']).to_string())));
+    }
+    if let Some(path) = file {
+        let content = std::fs::read_to_string(path).map_err(|e| Error::io(&format!("Failed to read {} file {}", what, path.display()), e))?;
+        // 只取第一行，忽略编辑器添加的换行
+        return Ok(Some(Secret(content.lines().next().unwrap_or_default().to_string())));
+    }
+    Ok(value.or(env.map(Secret)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_20_41_37() {
+        let secret: Secret = "hunter2".parse().unwrap();
+        assert_eq!(format!("{:?}", Some(&secret)), "Some(***)");
+        assert_eq!(secret.clone().into_inner(), "hunter2");
+
+        // 按进程与测试区分文件名，并行或重复运行测试时互不影响
+        let path = std::env::temp_dir().join(format!("torrent-tidy-test-secret-{}-{}", std::process::id(), "test_2026_10_18_20_41_37"));
+        std::fs::write(&path, "from-file\r\nignored\n").unwrap();
+        let resolved = resolve(Some(secret.clone()), Some(&path), false, None, "password");
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(resolved.unwrap().unwrap().into_inner(), "from-file");
+        // 命令行参数优先于环境变量
+        let env = || Some("from-env".to_string());
+        assert_eq!(resolve(Some(secret), None, false, env(), "password").unwrap().unwrap().into_inner(), "hunter2");
+        assert_eq!(resolve(None, None, false, env(), "password").unwrap().unwrap().into_inner(), "from-env");
+    }
+}
@@ -0,0 +1,37 @@
+use crate::re::{PartTrace, Renamer, Rule, Scope};
+use std::io::{BufRead, BufReader, Read};
+
+/// 离线测试重命名规则：逐行读取名称，分别按种子名称与文件路径应用规则，打印结果与每条规则的匹配位置
+pub fn run(rules: &[Rule], renamer: &Renamer, input: impl Read) -> std::io::Result<()> {
+    for line in BufReader::new(input).lines() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        if line.is_empty() {
+            continue;
+        }
+
+        println!("{}", line);
+        println!("  name: {}", renamer.rename_torrent(line));
+        print_matches(rules, &[renamer.trace_torrent(line)]);
+        println!("  file: {}", renamer.rename_file(line));
+        print_matches(rules, &renamer.trace_file(line));
+    }
+    Ok(())
+}
+
+fn print_matches(rules: &[Rule], parts: &[PartTrace]) {
+    for part in parts {
+        let scope = match part.scope {
+            Scope::Name => "name",
+            Scope::TopFolder => "top-folder",
+            Scope::Folder => "folder",
+            Scope::Stem => "stem",
+            Scope::Extension => "extension",
+        };
+        for m in &part.matches {
+            let description = &rules[m.rule].description;
+            let rule = if description.is_empty() { format!("#{}", m.rule) } else { format!("#{} ({})", m.rule, description) };
+            println!("    {} {} [{}, {}) {:?}", rule, scope, m.start, m.end, m.text);
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::error::Error;
+use crate::log;
+use crate::plan::{ConflictStrategy, OutputFormat, RenameKind, RenamePlan};
+use crate::client::{self, Backend, TorrentClient, TorrentFile, TorrentFilter, TorrentInfo};
+use crate::re::Renamer;
+use std::time::{Duration, Instant};
+
+/// 等待元数据时两次查询之间的最长间隔
+const MAX_METADATA_BACKOFF: Duration = Duration::from_secs(30);
+
+/// 处理每个种子时共用的客户端、规则与选项
+pub struct Tidy {
+    pub client: &'static Backend,
+    pub renamer: Renamer,
+    pub on_conflict: ConflictStrategy,
+    pub dry_run: bool,
+    pub output: OutputFormat,
+    pub metadata_timeout: Duration,
+    /// 成功处理后为种子添加的标签
+    pub mark_tag: Option<String>,
+}
+
+/// 单个种子的处理结果
+#[derive(Debug)]
+pub struct Summary {
+    pub hash: String,
+    pub name: String,
+    pub planned: usize,
+    pub failed: usize,
+    pub conflicts: usize,
+    pub error: Option<Error>,
+    /// 单个目录或文件重命名失败的错误，不影响其它重命名
+    pub failures: Vec<Error>,
+    /// 试运行时只有计划，没有实际改名
+    pub dry_run: bool,
+}
+
+impl Summary {
+    fn new(torrent: &TorrentInfo) -> Self {
+        Self {
+            hash: torrent.hash.clone(),
+            name: torrent.name.clone(),
+            planned: 0,
+            failed: 0,
+            conflicts: 0,
+            error: None,
+            failures: vec![],
+            dry_run: false,
+        }
+    }
+
+    /// 显式指定但不存在的种子，计为未找到的错误
+    pub fn not_found(hash: &str) -> Self {
+        Self {
+            hash: hash.to_string(),
+            name: String::new(),
+            planned: 0,
+            failed: 0,
+            conflicts: 0,
+            error: Some(Error::NotFound(format!("No torrent found with hash: {}", hash))),
+            failures: vec![],
+            dry_run: false,
+        }
+    }
+
+    /// 处理结果对应的错误：优先取导致整个种子失败的错误，其次取第一个重命名失败的错误
+    pub fn first_error(&self) -> Option<&Error> {
+        self.error.as_ref().or(self.failures.first())
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.name.as_str() {
+            "" => write!(f, "{}: ", self.hash)?,
+            name => write!(f, "{} {}: ", self.hash, name)?,
+        }
+        match &self.error {
+            Some(e) => write!(f, "error: {}", e),
+            None if self.dry_run => write!(f, "{} planned, {} conflicts", self.planned, self.conflicts),
+            None => write!(
+                f,
+                "{} renamed, {} failed, {} conflicts",
+                self.planned - self.failed,
+                self.failed,
+                self.conflicts
+            ),
+        }
+    }
+}
+
+impl Tidy {
+    /// 获取文件列表、计算重命名计划并执行（或仅打印计划）。`wait` 为 false 时不等待磁力链接的元数据，直接跳过该种子
+    pub async fn process(&self, torrent: TorrentInfo, wait: bool) -> Summary {
+        let mut summary = Summary { dry_run: self.dry_run, ..Summary::new(&torrent) };
+        let (torrent, files) = match self.wait_for_metadata(torrent, wait).await {
+            Ok(Some(fetched)) => fetched,
+            Ok(None) => {
+                log!("Skipping torrent {}, its metadata is not available yet", summary.hash);
+                return summary;
+            }
+            Err(e) => {
+                summary.error = Some(e);
+                return summary;
+            }
+        };
+        summary.name = torrent.name.clone();
+        let (client, hash) = (self.client, torrent.hash.as_str());
+
+        let mut plan = RenamePlan::new(&torrent, &files, &self.renamer, self.on_conflict);
+        // 不支持单独修改种子名称的客户端跳过种子名称的重命名，试运行打印的计划中也不包含它
+        if let Some(rename) = plan.torrent_rename() && !client.supports_torrent_rename() {
+            log!("Skipping torrent rename {} -> {}, not supported by {}", rename.old, rename.new, client.name());
+            plan.renames.retain(|r| r.kind != RenameKind::Torrent);
+        }
+        let torrent_rename = plan.torrent_rename();
+        summary.planned = plan.renames.len();
+        summary.conflicts = plan.conflicts.len();
+
+        if self.dry_run {
+            plan.print(self.output);
+            return summary;
+        }
+        for conflict in &plan.conflicts {
+            log!("Conflict ({:?}): {} <- {}", plan.strategy, conflict.target, conflict.sources.join(", "));
+        }
+        if plan.strategy == ConflictStrategy::Abort && !plan.conflicts.is_empty() {
+            log!("Aborted renaming torrent {} due to conflicts", hash);
+            summary.error = Some(Error::Conflict("Aborted due to conflicts".to_string()));
+            return summary;
+        }
+
+        // 种子名称与目录、文件互不影响，可以同时进行；先由深到浅重命名目录，再重命名已位于新目录下的文件
+        let rename_torrent = async {
+            match torrent_rename {
+                Some(rename) => client::rename_torrent(client, hash, rename).await.map_err(|e| (1, e)),
+                None => Ok(()),
+            }
+        };
+        let rename_paths = async {
+            let folder_renames: Vec<_> = plan.folder_renames().cloned().collect();
+            let file_renames: Vec<_> = plan.file_renames().cloned().collect();
+            // 目录改名失败时之后的目录与所有文件都不再处理，一并计为失败
+            let unattempted = folder_renames.len() + file_renames.len();
+            client::rename_folders(client, hash, folder_renames)
+                .await
+                .map_err(|(done, e)| (unattempted - done, e))?;
+            Ok(client::rename_files(client, hash, file_renames).await)
+        };
+        let (torrent_result, paths_result) = tokio::join!(rename_torrent, rename_paths);
+        client.finish_renames(hash).await;
+
+        if let Err((failed, e)) = torrent_result {
+            log!("Task failed: {}", e);
+            summary.failed += failed;
+            summary.failures.push(e);
+        }
+        match paths_result {
+            Ok(errors) => {
+                summary.failed += errors.len();
+                summary.failures.extend(errors);
+            }
+            Err((failed, e)) => {
+                log!("Task failed: {}", e);
+                summary.failed += failed;
+                summary.failures.push(e);
+            }
+        }
+
+        if let Some(tag) = &self.mark_tag && summary.first_error().is_none() {
+            match client.add_tags(hash, std::slice::from_ref(tag)).await {
+                Ok(()) => log!("Tagged torrent {} with {}", hash, tag),
+                Err(e) => log!("Failed to tag torrent {}: {}", hash, e),
+            }
+        }
+        summary
+    }
+
+    /// 获取文件列表。刚添加的磁力链接还没有元数据，文件列表为空且名称就是哈希值，
+    /// 此时按指数退避重新查询，直到元数据到达或超过 `metadata_timeout`；不等待时返回 `None`
+    async fn wait_for_metadata(&self, mut torrent: TorrentInfo, wait: bool) -> Result<Option<(TorrentInfo, Vec<TorrentFile>)>, Error> {
+        let client = self.client;
+        let start = Instant::now();
+        let mut backoff = Duration::from_secs(1);
+        loop {
+            let files = client.get_files(&torrent.hash).await?;
+            if !files.is_empty() && !torrent.name.eq_ignore_ascii_case(&torrent.hash) {
+                return Ok(Some((torrent, files)));
+            }
+            if !wait {
+                return Ok(None);
+            }
+
+            let remaining = self.metadata_timeout.saturating_sub(start.elapsed());
+            if remaining.is_zero() {
+                return Err(Error::Network(format!(
+                    "Timed out after {} waiting for metadata",
+                    humantime::format_duration(self.metadata_timeout)
+                )));
+            }
+            // 按毫秒取整，日志中的时长更易读
+            let delay = backoff.min(Duration::from_millis(remaining.as_millis() as u64));
+            log!("Waiting for metadata of torrent {}, retrying in {}", torrent.hash, humantime::format_duration(delay));
+            tokio::time::sleep(delay).await;
+            backoff = (backoff * 2).min(MAX_METADATA_BACKOFF);
+
+            // 元数据到达后名称会变为种子内的真实名称，需要重新获取
+            let filter = TorrentFilter { hashes: vec![torrent.hash.clone()], ..Default::default() };
+            match client.list_torrents(&filter).await?.pop() {
+                Some(info) => torrent = info,
+                None => return Err(Error::NotFound(format!("No torrent found with hash: {}", torrent.hash))),
+            }
+        }
+    }
+}
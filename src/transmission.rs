@@ -0,0 +1,247 @@
+use crate::client::{self, TorrentClient, TorrentFile, TorrentFilter, TorrentInfo};
+use crate::error::Error;
+use crate::plan::Rename;
+use crate::retry;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::RwLock;
+
+/// Transmission 要求每个 RPC 请求带上的会话 ID，缺失或过期时返回 409 并在同名响应头中给出新的 ID
+const SESSION_ID_HEADER: &str = "X-Transmission-Session-Id";
+
+/// Transmission RPC 客户端
+/// https://github.com/transmission/transmission/blob/main/docs/rpc-spec.md
+pub struct Transmission {
+    client: Client,
+    rpc_url: String,
+    credentials: Option<(String, String)>,
+    session_id: RwLock<String>,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcResponse {
+    result: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Deserialize, Debug)]
+struct TorrentList {
+    torrents: Vec<RpcTorrent>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RpcTorrent {
+    #[serde(default)]
+    hash_string: String,
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    files: Vec<RpcFile>,
+    #[serde(default)]
+    labels: Vec<String>,
+    #[serde(default)]
+    status: u8,
+    #[serde(default)]
+    percent_done: f64,
+    #[serde(default)]
+    error: i64,
+}
+
+#[derive(Deserialize, Debug)]
+struct RpcFile {
+    name: String,
+    #[serde(default)]
+    length: u64,
+}
+
+impl RpcTorrent {
+    /// 按 qBittorrent 的状态名称筛选，只支持 Transmission 中有对应状态的名称
+    fn matches_state(&self, state: &str) -> Result<bool, Error> {
+        // status: 0 已停止, 1 等待校验, 2 校验中, 3 等待下载, 4 下载中, 5 等待做种, 6 做种中
+        Ok(match state {
+            "all" => true,
+            "downloading" => matches!(self.status, 3 | 4),
+            "seeding" => matches!(self.status, 5 | 6),
+            "completed" => self.percent_done >= 1.0,
+            "stopped" | "paused" => self.status == 0,
+            "checking" => matches!(self.status, 1 | 2),
+            "errored" => self.error != 0,
+            _ => {
+                return Err(Error::Unsupported(format!(
+                    "Transmission does not support the state filter {:?}, use one of all, downloading, seeding, completed, stopped, checking, errored",
+                    state
+                )));
+            }
+        })
+    }
+}
+
+impl Transmission {
+    /// `url` 可以是 RPC 地址本身，也可以是 Web 界面的根地址
+    pub fn new(client: Client, url: String, credentials: Option<(String, String)>) -> Self {
+        let url = url.trim_end_matches('/');
+        let rpc_url = if url.ends_with("/rpc") { url.to_string() } else { format!("{}/transmission/rpc", url) };
+        Self { client, rpc_url, credentials, session_id: RwLock::new(String::new()) }
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.credentials {
+            Some((username, password)) => request.basic_auth(username, Some(password)),
+            None => request,
+        }
+    }
+
+    /// 发送一个空请求，从 409 响应中取得新的会话 ID
+    async fn refresh_session(&self) -> Result<(), Error> {
+        let response = self
+            .authorize(self.client.post(&self.rpc_url))
+            .send()
+            .await
+            .map_err(|e| Error::http("Failed to connect to Transmission", e))?;
+        if let Some(id) = response.headers().get(SESSION_ID_HEADER).and_then(|id| id.to_str().ok()) {
+            *self.session_id.write().unwrap() = id.to_string();
+            return Ok(());
+        }
+        response.error_for_status().map_err(|e| Error::http("Failed to connect to Transmission", e))?;
+        Err(Error::Decode(format!("Transmission did not return a {} header", SESSION_ID_HEADER)))
+    }
+
+    async fn call(&self, method: &str, arguments: Value) -> Result<Value, Error> {
+        // 读取请求不会被当作已生效，因此总有结果
+        self.call_unless_applied(method, arguments, || async { false }).await.map(|v| v.unwrap())
+    }
+
+    /// 调用 RPC 方法，重试前先用 `applied` 确认上一次请求是否已经生效，已生效时返回 `None`
+    async fn call_unless_applied<F: Future<Output = bool>>(
+        &self,
+        method: &str,
+        arguments: Value,
+        applied: impl Fn() -> F,
+    ) -> Result<Option<Value>, Error> {
+        let body = json!({ "method": method, "arguments": arguments });
+        // 会话 ID 缺失或过期时更新后重新发送一次
+        for _ in 0..2 {
+            let session_id = self.session_id.read().unwrap().clone();
+            let request = self.authorize(self.client.post(&self.rpc_url)).header(SESSION_ID_HEADER, session_id).json(&body);
+            let response = match retry::send_unless_applied(request, &applied).await {
+                Ok(Some(response)) => response,
+                Ok(None) => return Ok(None),
+                Err(e) if e.status() == Some(StatusCode::CONFLICT) => {
+                    self.refresh_session().await?;
+                    continue;
+                }
+                Err(e) => return Err(Error::http(&format!("Transmission RPC {} failed", method), e)),
+            };
+            let response: RpcResponse = response
+                .json()
+                .await
+                .map_err(|e| Error::http(&format!("Failed to parse Transmission RPC {} response", method), e))?;
+            return match response.result.as_str() {
+                "success" => Ok(Some(response.arguments)),
+                result if result.contains("exist") => Err(Error::Conflict(format!("Transmission RPC {} failed: {}", method, result))),
+                result => Err(Error::Network(format!("Transmission RPC {} failed: {}", method, result))),
+            };
+        }
+        Err(Error::Auth("Transmission keeps rejecting the session id".to_string()))
+    }
+
+    async fn get_torrents(&self, hashes: &[String], fields: &[&str]) -> Result<Vec<RpcTorrent>, Error> {
+        let mut arguments = json!({ "fields": fields });
+        if !hashes.is_empty() {
+            arguments["ids"] = json!(hashes);
+        }
+        let list: TorrentList = serde_json::from_value(self.call("torrent-get", arguments).await?)
+            .map_err(|e| Error::Decode(format!("Failed to parse Transmission torrent list: {}", e)))?;
+        Ok(list.torrents)
+    }
+}
+
+impl TorrentClient for Transmission {
+    async fn list_torrents(&self, filter: &TorrentFilter) -> Result<Vec<TorrentInfo>, Error> {
+        if filter.category.is_some() {
+            return Err(Error::Unsupported("Transmission has no categories, use --tag to select torrents by label".to_string()));
+        }
+        let fields = ["hashString", "name", "labels", "status", "percentDone", "error"];
+        let mut torrents = vec![];
+        for torrent in self.get_torrents(&filter.hashes, &fields).await? {
+            if let Some(tag) = &filter.tag && !torrent.labels.contains(tag) {
+                continue;
+            }
+            if let Some(state) = &filter.state && !torrent.matches_state(state)? {
+                continue;
+            }
+            torrents.push(TorrentInfo { hash: torrent.hash_string, name: torrent.name });
+        }
+        Ok(torrents)
+    }
+
+    async fn get_files(&self, hash: &str) -> Result<Vec<TorrentFile>, Error> {
+        let torrent = self
+            .get_torrents(&[hash.to_string()], &["files"])
+            .await?
+            .pop()
+            .ok_or_else(|| Error::NotFound(format!("No torrent found with hash: {}", hash)))?;
+        let files = torrent.files.into_iter().enumerate();
+        Ok(files.map(|(index, file)| TorrentFile { name: file.name, index: index as u32, size: file.length }).collect())
+    }
+
+    /// Transmission 的种子名称就是顶层目录或单个文件的名称，随目录或文件的重命名一起改变
+    fn supports_torrent_rename(&self) -> bool {
+        false
+    }
+
+    async fn rename_torrent(&self, _hash: &str, _name: &str) -> Result<(), Error> {
+        Err(Error::Unsupported("Transmission can not rename a torrent apart from its top-level path".to_string()))
+    }
+
+    async fn rename_path(&self, hash: &str, rename: &Rename, is_dir: bool) -> Result<(), Error> {
+        // torrent-rename-path 只修改路径的最后一级
+        let old_parent = rename.old.rsplit_once('/').map_or("", |(parent, _)| parent);
+        let (new_parent, new_name) = rename.new.rsplit_once('/').unwrap_or(("", &rename.new));
+        if old_parent != new_parent {
+            return Err(Error::Unsupported(format!(
+                "Transmission can only rename the last component of a path: {} -> {}",
+                rename.old, rename.new
+            )));
+        }
+        let arguments = json!({ "ids": [hash], "path": rename.old, "name": new_name });
+        self.call_unless_applied("torrent-rename-path", arguments, || client::path_renamed(self, hash, rename, is_dir))
+            .await?;
+        Ok(())
+    }
+
+    async fn add_tags(&self, hash: &str, tags: &[String]) -> Result<(), Error> {
+        // torrent-set 会覆盖全部 label，需要先合并已有的 label
+        let mut labels = match self.get_torrents(&[hash.to_string()], &["labels"]).await?.pop() {
+            Some(torrent) => torrent.labels,
+            None => return Err(Error::NotFound(format!("No torrent found with hash: {}", hash))),
+        };
+        for tag in tags {
+            if !labels.contains(tag) {
+                labels.push(tag.clone());
+            }
+        }
+        self.call("torrent-set", json!({ "ids": [hash], "labels": labels })).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_2026_10_18_18_20_14() {
+        let rpc_url = |url: &str| Transmission::new(Client::new(), url.to_string(), None).rpc_url;
+        assert_eq!(rpc_url("http://localhost:9091/"), "http://localhost:9091/transmission/rpc");
+        assert_eq!(rpc_url("http://localhost:9091/transmission/rpc"), "http://localhost:9091/transmission/rpc");
+
+        let torrent: RpcTorrent = serde_json::from_str(r#"{"hashString": "a", "status": 4, "percentDone": 0.5}"#).unwrap();
+        assert!(torrent.matches_state("downloading").unwrap());
+        assert!(!torrent.matches_state("completed").unwrap());
+        assert!(matches!(torrent.matches_state("stalled"), Err(Error::Unsupported(_))));
+    }
+}
@@ -0,0 +1,51 @@
+use crate::journal::JournalEntry;
+use crate::plan::RenameKind;
+use crate::client::{self, Backend, TorrentClient};
+use crate::error::Error;
+use crate::log;
+use std::collections::BTreeSet;
+use std::time::SystemTime;
+
+/// 按记录的相反顺序撤销重命名：先把文件改回原名，再由浅到深恢复目录，最后恢复种子名称。
+/// 失败的记录不影响其它记录，返回第一个失败的错误
+pub async fn run(
+    client: &'static Backend,
+    entries: Vec<JournalEntry>,
+    hash: Option<&str>,
+    since: Option<SystemTime>,
+) -> Result<(), Error> {
+    let selected: Vec<JournalEntry> = entries
+        .into_iter()
+        .filter(|e| hash.is_none_or(|h| e.hash.eq_ignore_ascii_case(h)))
+        .filter(|e| since.is_none_or(|since| e.time().is_some_and(|t| t >= since)))
+        .collect();
+    log!("Reverting {} journal entries", selected.len());
+
+    let mut failures = vec![];
+    for entry in selected.iter().rev() {
+        let rename = entry.inverse();
+        let result = match entry.kind {
+            RenameKind::Torrent => client::rename_torrent(client, &entry.hash, &rename).await,
+            RenameKind::Folder => client::rename_folders(client, &entry.hash, vec![rename]).await.map_err(|(_, e)| e),
+            RenameKind::File => match client::rename_files(client, &entry.hash, vec![rename]).await.pop() {
+                Some(e) => Err(e),
+                None => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            // 文件重命名的失败已由 rename_files 记录
+            if entry.kind != RenameKind::File {
+                log!("Failed to undo {} -> {}: {}", entry.old, entry.new, e);
+            }
+            failures.push(e);
+        }
+    }
+    for hash in selected.iter().map(|e| e.hash.as_str()).collect::<BTreeSet<_>>() {
+        client.finish_renames(hash).await;
+    }
+    log!("Reverted {} journal entries, {} failed", selected.len() - failures.len(), failures.len());
+    match failures.into_iter().next() {
+        Some(e) => Err(e),
+        None => Ok(()),
+    }
+}
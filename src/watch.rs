@@ -0,0 +1,139 @@
+use crate::error::Error;
+use crate::log;
+use crate::client::TorrentInfo;
+use crate::q_bit::{MainData, QBittorrent};
+use crate::tidy::Tidy;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// 正在获取元数据的磁力链接所处的状态
+const METADATA_STATES: [&str; 2] = ["metaDL", "forcedMetaDL"];
+
+/// 已知种子的名称与状态
+#[derive(Debug, Default)]
+struct Known {
+    name: String,
+    state: String,
+}
+
+impl Known {
+    fn has_metadata(&self) -> bool {
+        !METADATA_STATES.contains(&self.state.as_str())
+    }
+}
+
+/// 根据 sync/maindata 的增量数据维护种子列表，找出需要重命名的种子
+#[derive(Debug, Default)]
+struct Tracker {
+    torrents: HashMap<String, Known>,
+    started: bool,
+}
+
+impl Tracker {
+    /// 合并一次增量数据，返回新添加且已有元数据的种子，以及刚获取到元数据的种子。
+    /// 启动时已存在的种子不视为新种子
+    fn apply(&mut self, data: MainData) -> Vec<TorrentInfo> {
+        if data.full_update {
+            // 全量数据中不再出现的种子已被删除
+            self.torrents.retain(|hash, _| data.torrents.contains_key(hash));
+        }
+        for hash in &data.torrents_removed {
+            self.torrents.remove(hash);
+        }
+
+        let mut ready = vec![];
+        for (hash, patch) in data.torrents {
+            let is_new = !self.torrents.contains_key(&hash);
+            let known = self.torrents.entry(hash.clone()).or_default();
+            let had_metadata = !is_new && known.has_metadata();
+            if let Some(name) = patch.name { known.name = name; }
+            if let Some(state) = patch.state { known.state = state; }
+
+            let metadata_arrived = !is_new && !had_metadata && known.has_metadata();
+            if (is_new && self.started && known.has_metadata()) || metadata_arrived {
+                ready.push(TorrentInfo { hash, name: known.name.clone() });
+            }
+        }
+        self.started = true;
+        ready
+    }
+}
+
+/// 持续轮询 sync/maindata，对新添加或刚获取到元数据的种子执行重命名。
+/// 会话失效时使用提供的用户名和密码重新登录
+pub async fn run(tidy: &'static Tidy, qbit: &QBittorrent, interval: Duration) {
+    let mut tracker = Tracker::default();
+    let mut rid = 0;
+    let mut tasks = JoinSet::new();
+    log!("Watching qBittorrent every {}", humantime::format_duration(interval));
+
+    loop {
+        match qbit.sync_maindata(rid).await {
+            Ok(data) => {
+                rid = data.rid;
+                for torrent in tracker.apply(data) {
+                    log!("Renaming torrent {} ({})", torrent.hash, torrent.name);
+                    tasks.spawn(tidy.process(torrent, true));
+                }
+            }
+            Err(Error::Auth(_)) => {
+                log!("Session expired, logging in again");
+                if let Err(e) = qbit.login().await {
+                    log!("{}", e);
+                }
+            }
+            Err(e) => log!("{}", e),
+        }
+
+        while let Some(res) = tasks.try_join_next() {
+            match res {
+                Ok(summary) => log!("{}", summary),
+                Err(e) => log!("Task execution failed: {}", e),
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::q_bit::TorrentPatch;
+
+    #[test]
+    fn test_2026_10_18_16_25_48() {
+        let patch = |name: Option<&str>, state: Option<&str>| TorrentPatch {
+            name: name.map(String::from),
+            state: state.map(String::from),
+        };
+        let data = |rid, full_update, torrents: Vec<(&str, TorrentPatch)>| MainData {
+            rid,
+            full_update,
+            torrents: torrents.into_iter().map(|(h, p)| (h.to_string(), p)).collect(),
+            torrents_removed: vec![],
+        };
+        let hashes = |ready: Vec<TorrentInfo>| ready.into_iter().map(|t| t.hash).collect::<Vec<_>>();
+        let mut tracker = Tracker::default();
+
+        // 启动时已存在的种子不处理，但仍在获取元数据的种子在元数据到达后处理
+        let ready = tracker.apply(data(1, true, vec![
+            ("old", patch(Some("Old"), Some("uploading"))),
+            ("magnet", patch(Some("magnet"), Some("metaDL"))),
+        ]));
+        assert!(ready.is_empty());
+
+        let ready = tracker.apply(data(2, false, vec![
+            ("new", patch(Some("New"), Some("downloading"))),
+            ("magnet2", patch(Some("magnet2"), Some("metaDL"))),
+        ]));
+        assert_eq!(hashes(ready), ["new"]);
+
+        let ready = tracker.apply(data(3, false, vec![("magnet", patch(Some("Real.Name"), Some("downloading")))]));
+        assert_eq!(ready[0].name, "Real.Name");
+
+        // 仅名称变化（例如刚被重命名）不会再次处理
+        let ready = tracker.apply(data(4, false, vec![("new", patch(Some("New Name"), None))]));
+        assert!(ready.is_empty());
+    }
+}